@@ -10,6 +10,17 @@ var holo_blocks: texture_2d<f32>;
 @group(1) @binding(3)
 var holo_blocks_sampler: sampler;
 
+struct HologramSettings {
+    tint: vec4<f32>,
+    scanline_density: f32,
+    scanline_dimming: f32,
+    brightness: f32,
+    flicker: f32,
+    time: f32,
+};
+@group(1) @binding(4)
+var<uniform> settings: HologramSettings;
+
 @fragment
 fn fragment(
     @builtin(position) position: vec4<f32>,
@@ -17,10 +28,15 @@ fn fragment(
 ) -> @location(0) vec4<f32> {
     // Get screen position with coordinates from 0 to 1
     let uv = position.xy / vec2<f32>(view.width, view.height);
-    let strength = select(1.0, 0.7, uv.y % 0.01 < 0.005);
+    let spacing = 1.0 / settings.scanline_density;
+    let strength = select(1.0, 1.0 - settings.scanline_dimming, uv.y % spacing < spacing * 0.5);
+    // Two sines at unrelated frequencies so the flicker does not look periodic.
+    let flicker = 1.0 - settings.flicker
+        * (0.5 + 0.5 * sin(settings.time * 23.0))
+        * (0.5 + 0.5 * sin(settings.time * 7.3));
 
     var output_color = 3.0 * textureSample(regular_blocks, regular_blocks_sampler, uv)
-        + strength * clamp(strength * 3.0 * textureSample(holo_blocks, holo_blocks_sampler, uv) - 0.1, vec4(0.0), vec4(1.0));
+        + flicker * settings.tint * strength * clamp(strength * 3.0 * textureSample(holo_blocks, holo_blocks_sampler, uv) - 0.1, vec4(0.0), vec4(1.0));
 
-    return output_color * 0.8;
+    return output_color * settings.brightness;
 }
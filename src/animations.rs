@@ -1,26 +1,144 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, scene::SceneInstance};
 
-use crate::simulation::SimulationState;
+use crate::{hologramify::PleaseHologramifyThis, simulation::SimulationState, structure::Beam};
+
+/// How the progress through a tick maps onto the distance moved.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Easing {
+    Linear,
+    /// Speeds up from rest.
+    In,
+    /// Slows down to rest.
+    Out,
+    InOut,
+}
+
+impl Easing {
+    /// Picks the curve for a move, so that a part moving for several ticks in
+    /// a row only speeds up at the start and slows down at the end.
+    pub fn for_move(starting: bool, stopping: bool) -> Self {
+        match (starting, stopping) {
+            (false, false) => Easing::Linear,
+            (true, false) => Easing::In,
+            (false, true) => Easing::Out,
+            (true, true) => Easing::InOut,
+        }
+    }
+
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::In => t * t,
+            Easing::Out => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
 
 #[derive(Component)]
 pub enum Animation {
     Stationary,
-    Lerp(Vec3, Vec3),
+    Lerp(Vec3, Vec3, Easing),
+}
+
+/// Put on a freshly spawned structure whose scenes may not have been
+/// instantiated yet. The structure it replaces stays around until then, so the
+/// part does not disappear for a frame.
+#[derive(Component, Default)]
+pub struct PendingStructure {
+    frames: u32,
 }
 
+/// Gives up waiting after this many frames, in case a scene never spawns.
+const PENDING_FRAMES: u32 = 30;
+
 fn animation_system(
     mut animated_objs: Query<(&mut Transform, &Animation)>,
     simulation_state: Option<Res<SimulationState>>,
 ) {
     if let Some(simulation_state) = simulation_state {
         for (mut transform, animation) in animated_objs.iter_mut() {
-            match animation {
-                Animation::Stationary => transform.translation = Vec3::ZERO,
-                Animation::Lerp(a, b) => {
-                    transform.translation = a.lerp(*b, simulation_state.tick_progress())
+            let translation = match animation {
+                Animation::Stationary => Vec3::ZERO,
+                Animation::Lerp(a, b, easing) => {
+                    a.lerp(*b, easing.apply(simulation_state.tick_progress()))
                 }
+            };
+            // Avoids marking every part as changed every frame.
+            if transform.translation != translation {
+                transform.translation = translation;
+            }
+        }
+    }
+}
+
+fn beam_length_system(
+    mut beams: Query<(&mut Transform, &Beam)>,
+    simulation_state: Option<Res<SimulationState>>,
+) {
+    // Outside of a run beams keep the length they were spawned with, the
+    // lengths of the last run are still in `beams` until the next one starts.
+    let simulation_state = match simulation_state {
+        Some(simulation_state) if simulation_state.is_started() => simulation_state,
+        _ => return,
+    };
+    let progress = simulation_state.tick_progress();
+    for (mut transform, beam) in beams.iter_mut() {
+        // Beams keep the length they were spawned with until the first tick.
+        let tick = match simulation_state.beams.get(&beam.for_block) {
+            Some(tick) => tick,
+            None => continue,
+        };
+        let (from, to) = tick.length;
+        let length = from + (to - from) * tick.easing.apply(progress);
+        if length <= 0.0 {
+            transform.scale = Vec3::ZERO;
+        } else {
+            transform.scale.x = length;
+            // Beams that were hidden need their thickness back.
+            if transform.scale.y == 0.0 {
+                transform.scale.y = 1.0;
+                transform.scale.z = 1.0;
+            }
+        }
+    }
+}
+
+fn swap_pending_structures(
+    mut commands: Commands,
+    mut pending: Query<(Entity, &Parent, &mut PendingStructure)>,
+    children: Query<&Children>,
+    not_ready: Query<
+        (),
+        Or<(
+            (With<Handle<Scene>>, Without<SceneInstance>),
+            With<PleaseHologramifyThis>,
+        )>,
+    >,
+) {
+    for (entity, parent, mut pending_structure) in pending.iter_mut() {
+        let siblings = match children.get(parent.get()) {
+            Ok(siblings) => siblings,
+            Err(_) => continue,
+        };
+        // An even newer structure will take care of this one.
+        if siblings.last() != Some(&entity) {
+            continue;
+        }
+        pending_structure.frames += 1;
+        let ready = children.get(entity).map_or(true, |blocks| {
+            blocks.iter().all(|&block| not_ready.get(block).is_err())
+        });
+        if !ready && pending_structure.frames < PENDING_FRAMES {
+            continue;
+        }
+        for &sibling in siblings.iter() {
+            if sibling != entity {
+                commands.entity(sibling).despawn_recursive();
             }
         }
+        commands.entity(entity).remove::<PendingStructure>();
     }
 }
 
@@ -28,6 +146,8 @@ pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(animation_system);
+        app.add_system(animation_system)
+            .add_system(beam_length_system)
+            .add_system(swap_pending_structures);
     }
 }
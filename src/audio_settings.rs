@@ -0,0 +1,147 @@
+use bevy::{audio::AudioSink, prelude::*};
+
+use crate::config::{load_config, save_config};
+
+const AUDIO_FILE: &str = "audio.txt";
+
+/// Volumes are multiplied onto the volume each sound was mixed at, so 1.0
+/// leaves everything as loud as it originally was.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+    pub muted: bool,
+    pub music_muted: bool,
+    pub effects_muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 1.0,
+            effects: 1.0,
+            muted: false,
+            music_muted: false,
+            effects_muted: false,
+        }
+    }
+}
+
+/// The sink of the background music, kept so its volume can follow the
+/// settings while it plays.
+pub struct Music(pub Handle<AudioSink>);
+
+impl AudioSettings {
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let entries = match load_config(AUDIO_FILE) {
+            Some(entries) => entries,
+            None => return settings,
+        };
+        for (name, value) in entries {
+            let ok = match name.as_str() {
+                "master" => value.parse().map(|x| settings.master = x).is_ok(),
+                "music" => value.parse().map(|x| settings.music = x).is_ok(),
+                "effects" => value.parse().map(|x| settings.effects = x).is_ok(),
+                "muted" => value.parse().map(|x| settings.muted = x).is_ok(),
+                "music_muted" => value.parse().map(|x| settings.music_muted = x).is_ok(),
+                "effects_muted" => value.parse().map(|x| settings.effects_muted = x).is_ok(),
+                _ => false,
+            };
+            if !ok {
+                println!("Ignoring \"{} = {}\" in {}", name, value, AUDIO_FILE);
+            }
+        }
+        settings.master = settings.master.clamp(0.0, 1.0);
+        settings.music = settings.music.clamp(0.0, 1.0);
+        settings.effects = settings.effects.clamp(0.0, 1.0);
+        settings
+    }
+
+    pub fn save(&self) {
+        let entries = [
+            ("master", self.master.to_string()),
+            ("music", self.music.to_string()),
+            ("effects", self.effects.to_string()),
+            ("muted", self.muted.to_string()),
+            ("music_muted", self.music_muted.to_string()),
+            ("effects_muted", self.effects_muted.to_string()),
+        ];
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
+        save_config(AUDIO_FILE, &entries);
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        if self.muted || self.music_muted {
+            0.0
+        } else {
+            self.master * self.music
+        }
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        if self.muted || self.effects_muted {
+            0.0
+        } else {
+            self.master * self.effects
+        }
+    }
+
+    /// Plays a sound effect at `volume` scaled by the effects settings.
+    pub fn play(&self, audio: &Audio, sound: &Handle<AudioSource>, volume: f32) {
+        self.play_with_speed(audio, sound, volume, 1.0);
+    }
+
+    /// Like `play`, but a speed other than 1.0 also shifts the pitch.
+    pub fn play_with_speed(
+        &self,
+        audio: &Audio,
+        sound: &Handle<AudioSource>,
+        volume: f32,
+        speed: f32,
+    ) {
+        let volume = volume * self.effects_volume();
+        if volume > 0.0 {
+            audio.play_with_settings(
+                sound.clone(),
+                PlaybackSettings {
+                    speed,
+                    ..PlaybackSettings::ONCE.with_volume(volume)
+                },
+            );
+        }
+    }
+}
+
+/// The volume the music track was mixed at.
+pub const MUSIC_VOLUME: f32 = 0.3;
+
+fn update_music_volume(
+    settings: Res<AudioSettings>,
+    music: Option<Res<Music>>,
+    sinks: Res<Assets<AudioSink>>,
+) {
+    let music = match music {
+        Some(music) => music,
+        None => return,
+    };
+    // The sink only exists once the music has loaded, so this keeps trying
+    // every frame instead of only when the settings change.
+    if let Some(sink) = sinks.get(&music.0) {
+        sink.set_volume(MUSIC_VOLUME * settings.music_volume());
+    }
+}
+
+pub struct AudioSettingsPlugin;
+
+impl Plugin for AudioSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioSettings::load())
+            .add_system(update_music_volume);
+    }
+}
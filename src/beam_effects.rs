@@ -0,0 +1,111 @@
+//! Makes beams show what they did on the last tick, using what the simulation
+//! records in `SimulationState::beams`.
+
+use bevy::prelude::*;
+
+use crate::{
+    block::BlockKind,
+    graphics_settings::GraphicsSettings,
+    simulation::{BeamTick, SimulationState},
+    structure::{Beam, BeamState},
+    tint::Tint,
+};
+
+/// Seconds a weld flash takes to fade out.
+const FLASH_TIME: f32 = 0.35;
+/// Animations are rounded to this many steps so that the tint cache does not
+/// fill up with materials that only differ by a rounding error.
+const STEPS: f32 = 8.0;
+
+fn quantize(value: f32) -> f32 {
+    (value * STEPS).round() / STEPS
+}
+
+fn flash(tick: &BeamTick, now: f64) -> f32 {
+    match tick.welded_at {
+        Some(welded_at) => (1.0 - (now - welded_at) as f32 / FLASH_TIME).max(0.0),
+        None => 0.0,
+    }
+}
+
+/// `None` while editing, when beams keep their usual look.
+fn beam_tint(tick: Option<&BeamTick>, recolor: Option<Color>, now: f64) -> Tint {
+    let time = (now % 3600.0) as f32;
+    let pulse = quantize((time * 6.0).sin() * 0.5 + 0.5);
+    let tick = match tick {
+        Some(tick) => tick,
+        None => {
+            return Tint {
+                recolor,
+                ..Tint::new(Color::WHITE)
+            }
+        }
+    };
+    let (color, emissive) = match tick.state {
+        BeamState::Idle => (Color::rgb(0.55, 0.55, 0.55), Color::BLACK),
+        BeamState::Pulling => (Color::WHITE, Color::rgb(0.2, 0.2, 0.2) * pulse),
+        BeamState::Welding => (Color::WHITE, Color::BLACK),
+        BeamState::Blocked => {
+            // A stutter rather than a smooth pulse, so it reads as a problem.
+            let on = (time * 4.0).fract() < 0.5;
+            let brightness = if on { 1.0 } else { 0.6 };
+            (Color::rgb(1.0, 0.35, 0.3) * brightness, Color::BLACK)
+        }
+    };
+    Tint {
+        color,
+        emissive: emissive + Color::WHITE * quantize(flash(tick, now)),
+        recolor,
+    }
+}
+
+fn beam_effects_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<GraphicsSettings>,
+    simulation_state: Option<Res<SimulationState>>,
+    mut beams: Query<(Entity, &Beam, &mut Transform, Option<&mut Tint>)>,
+) {
+    let palette = settings.palette();
+    let now = time.seconds_since_startup();
+    for (entity, beam, mut transform, current_tint) in beams.iter_mut() {
+        let tick = simulation_state
+            .as_ref()
+            .filter(|state| state.is_started())
+            .and_then(|state| state.beams.get(&beam.for_block));
+        let recolor = match beam.for_block.kind {
+            BlockKind::TractorBeamSource => palette.tractor_beam,
+            _ => palette.welder_beam,
+        };
+        let tint = beam_tint(tick, recolor, now);
+        match current_tint {
+            Some(mut current_tint) => {
+                if *current_tint != tint {
+                    *current_tint = tint;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(tint);
+            }
+        }
+        // The length is animated by `animations`, only the thickness is here.
+        if let Some(tick) = tick.filter(|_| transform.scale.x > 0.0) {
+            let time = (now % 3600.0) as f32;
+            let width = match tick.state {
+                BeamState::Pulling => 0.9 + 0.2 * quantize((time * 6.0).sin() * 0.5 + 0.5),
+                BeamState::Idle => 0.8,
+                _ => 1.0,
+            } + 0.6 * flash(tick, now);
+            transform.scale.y = width;
+            transform.scale.z = width;
+        }
+    }
+}
+
+pub struct BeamEffectsPlugin;
+
+impl Plugin for BeamEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(beam_effects_system);
+    }
+}
@@ -6,6 +6,7 @@ use bevy_mod_raycast::{RayCastMethod, RayCastSource};
 use crate::world::Position;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlockKind {
     DecoStructure,
     DecoStructure2,
@@ -37,6 +38,7 @@ impl BlockKind {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlockFacing {
     Px,
     Py,
@@ -62,6 +64,23 @@ impl BlockFacing {
         }
     }
 
+    pub fn from_offset(offset: Position) -> Option<Self> {
+        Self::all().into_iter().find(|x| x.offset() == offset)
+    }
+
+    /// Returns the facing this facing ends up pointing in after being rotated
+    /// by `rotation`, which should be a multiple of 90 degrees on each axis.
+    pub fn rotated_by(&self, rotation: Quat) -> Self {
+        let o = self.offset();
+        let rotated = rotation * Vec3::new(o.0 as f32, o.1 as f32, o.2 as f32);
+        let rotated = (
+            rotated.x.round() as i32,
+            rotated.y.round() as i32,
+            rotated.z.round() as i32,
+        );
+        Self::from_offset(rotated).unwrap()
+    }
+
     pub fn reverse(&self) -> Self {
         match self {
             Self::Px => Self::Nx,
@@ -87,6 +106,7 @@ impl BlockFacing {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub kind: BlockKind,
     pub facing: BlockFacing,
@@ -96,6 +116,10 @@ pub struct Block {
 #[derive(Component)]
 pub struct BlockRaycastSet;
 
+/// Put on the scene of every block that gets drawn.
+#[derive(Component)]
+pub struct BlockVisual(pub BlockKind);
+
 pub fn update_raycast_position_from_cursor(
     mut events: EventReader<CursorMoved>,
     mut source: Query<&mut RayCastSource<BlockRaycastSet>>,
@@ -106,3 +130,33 @@ pub fn update_raycast_position_from_cursor(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quarter_turns_around_z() {
+        let turns = [
+            BlockFacing::Px,
+            BlockFacing::Py,
+            BlockFacing::Nx,
+            BlockFacing::Ny,
+        ];
+        for (index, turn) in turns.into_iter().enumerate() {
+            let rotation = turn.rotation();
+            for (from, &facing) in turns.iter().enumerate() {
+                assert_eq!(facing.rotated_by(rotation), turns[(from + index) % 4]);
+            }
+            assert_eq!(BlockFacing::Pz.rotated_by(rotation), BlockFacing::Pz);
+            assert_eq!(BlockFacing::Nz.rotated_by(rotation), BlockFacing::Nz);
+        }
+    }
+
+    #[test]
+    fn rotation_turns_px_into_the_facing() {
+        for facing in BlockFacing::all() {
+            assert_eq!(BlockFacing::Px.rotated_by(facing.rotation()), facing);
+        }
+    }
+}
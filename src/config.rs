@@ -0,0 +1,29 @@
+//! Tiny `key = value` settings files, stored next to the executable's working
+//! directory. Failing to read or write one is never fatal since there is no
+//! file system on the web build.
+
+use std::fs;
+
+pub fn load_config(file_name: &str) -> Option<Vec<(String, String)>> {
+    let contents = fs::read_to_string(file_name).ok()?;
+    let entries = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim().to_owned(), value.trim().to_owned()))
+        })
+        .collect();
+    Some(entries)
+}
+
+pub fn save_config(file_name: &str, entries: &[(String, String)]) {
+    let mut contents = String::new();
+    for (key, value) in entries {
+        contents.push_str(&format!("{} = {}\n", key, value));
+    }
+    if let Err(err) = fs::write(file_name, contents) {
+        println!("Could not save {}: {}", file_name, err);
+    }
+}
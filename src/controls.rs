@@ -0,0 +1,425 @@
+use bevy::{
+    input::{keyboard::KeyboardInput, ButtonState},
+    prelude::*,
+};
+
+use crate::{
+    block::BlockFacing,
+    config::{load_config, save_config},
+    screens::OpenScreen,
+};
+
+const CONTROLS_FILE: &str = "controls.txt";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    /// 0 is forward, 1 is left, 2 is back and 3 is right.
+    MoveCamera(usize),
+    RotateFacing(BlockFacing),
+    /// Picks the nth block from the hotbar.
+    SelectBlock(usize),
+    NextBlockKind,
+    PreviousBlockKind,
+    NextFacing,
+    PreviousFacing,
+    /// Places or removes a block under the cursor, like a left click.
+    Click,
+    RemoveBlock,
+    Back,
+    PreviousPart,
+    NextPart,
+    ToggleSimulation,
+    ConfirmStart,
+    StopAskingAboutSplits,
+    ExportLevel,
+    SelectAll,
+    Copy,
+    Paste,
+    PasteAsNewPart,
+    ToggleControlsScreen,
+    ToggleAudioScreen,
+    ToggleGraphicsScreen,
+    ToggleMotionOverlay,
+    /// Goes back into a level that was just completed.
+    ImproveSolution,
+    CopySolutionCode,
+    PasteSolutionCode,
+}
+
+/// Which actions can never be in use at the same time, so that they can share
+/// keys. The camera does not move while a block is being placed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ActionContext {
+    Camera,
+    Placing,
+    Global,
+}
+
+impl Action {
+    fn context(&self) -> ActionContext {
+        match self {
+            Self::MoveCamera(_) => ActionContext::Camera,
+            Self::RotateFacing(_) => ActionContext::Placing,
+            _ => ActionContext::Global,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::MoveCamera(0) => "camera_forward".to_owned(),
+            Self::MoveCamera(1) => "camera_left".to_owned(),
+            Self::MoveCamera(2) => "camera_back".to_owned(),
+            Self::MoveCamera(_) => "camera_right".to_owned(),
+            Self::RotateFacing(facing) => format!("face_{:?}", facing).to_lowercase(),
+            Self::SelectBlock(index) => format!("select_block_{}", index + 1),
+            Self::NextBlockKind => "next_block_kind".to_owned(),
+            Self::PreviousBlockKind => "previous_block_kind".to_owned(),
+            Self::NextFacing => "next_facing".to_owned(),
+            Self::PreviousFacing => "previous_facing".to_owned(),
+            Self::Click => "click".to_owned(),
+            Self::RemoveBlock => "remove_block".to_owned(),
+            Self::Back => "back".to_owned(),
+            Self::PreviousPart => "previous_part".to_owned(),
+            Self::NextPart => "next_part".to_owned(),
+            Self::ToggleSimulation => "toggle_simulation".to_owned(),
+            Self::ConfirmStart => "confirm_start".to_owned(),
+            Self::StopAskingAboutSplits => "stop_asking_about_splits".to_owned(),
+            Self::ExportLevel => "export_level".to_owned(),
+            Self::SelectAll => "select_all".to_owned(),
+            Self::Copy => "copy".to_owned(),
+            Self::Paste => "paste".to_owned(),
+            Self::PasteAsNewPart => "paste_as_new_part".to_owned(),
+            Self::ToggleControlsScreen => "toggle_controls_screen".to_owned(),
+            Self::ToggleAudioScreen => "toggle_audio_screen".to_owned(),
+            Self::ToggleGraphicsScreen => "toggle_graphics_screen".to_owned(),
+            Self::ToggleMotionOverlay => "toggle_motion_overlay".to_owned(),
+            Self::ImproveSolution => "improve_solution".to_owned(),
+            Self::CopySolutionCode => "copy_solution_code".to_owned(),
+            Self::PasteSolutionCode => "paste_solution_code".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Binding {
+    pub key: KeyCode,
+    pub control: bool,
+}
+
+impl Binding {
+    fn key(key: KeyCode) -> Self {
+        Self {
+            key,
+            control: false,
+        }
+    }
+
+    fn control(key: KeyCode) -> Self {
+        Self { key, control: true }
+    }
+
+    /// `None` for keys that `parse` would not read back from the controls
+    /// file.
+    pub fn new(key: KeyCode, control: bool) -> Option<Self> {
+        BINDABLE_KEYS.contains(&key).then(|| Self { key, control })
+    }
+
+    pub fn describe(&self) -> String {
+        if self.control {
+            format!("Ctrl+{:?}", self.key)
+        } else {
+            format!("{:?}", self.key)
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let (control, key) = match text.strip_prefix("Ctrl+") {
+            Some(key) => (true, key),
+            None => (false, text),
+        };
+        let key = *BINDABLE_KEYS
+            .iter()
+            .find(|candidate| format!("{:?}", candidate) == key)?;
+        Some(Self { key, control })
+    }
+}
+
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Back,
+    KeyCode::Delete,
+    KeyCode::Insert,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Minus,
+    KeyCode::Equals,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Backslash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::Grave,
+];
+
+pub struct Bindings(pub Vec<(Action, Binding)>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Action::*;
+        use BlockFacing::*;
+        Self(vec![
+            (MoveCamera(0), Binding::key(KeyCode::W)),
+            (MoveCamera(1), Binding::key(KeyCode::A)),
+            (MoveCamera(2), Binding::key(KeyCode::S)),
+            (MoveCamera(3), Binding::key(KeyCode::D)),
+            (RotateFacing(Ny), Binding::key(KeyCode::Q)),
+            (RotateFacing(Pz), Binding::key(KeyCode::W)),
+            (RotateFacing(Nx), Binding::key(KeyCode::E)),
+            (RotateFacing(Px), Binding::key(KeyCode::A)),
+            (RotateFacing(Nz), Binding::key(KeyCode::S)),
+            (RotateFacing(Py), Binding::key(KeyCode::D)),
+            (SelectBlock(0), Binding::key(KeyCode::Key1)),
+            (SelectBlock(1), Binding::key(KeyCode::Key2)),
+            (SelectBlock(2), Binding::key(KeyCode::Key3)),
+            (SelectBlock(3), Binding::key(KeyCode::Key4)),
+            (PreviousBlockKind, Binding::key(KeyCode::Z)),
+            (NextBlockKind, Binding::key(KeyCode::X)),
+            (PreviousFacing, Binding::key(KeyCode::F)),
+            (NextFacing, Binding::key(KeyCode::R)),
+            (Back, Binding::key(KeyCode::Escape)),
+            (PreviousPart, Binding::key(KeyCode::Minus)),
+            (NextPart, Binding::key(KeyCode::Equals)),
+            (ToggleSimulation, Binding::key(KeyCode::Space)),
+            (ConfirmStart, Binding::key(KeyCode::Return)),
+            (StopAskingAboutSplits, Binding::key(KeyCode::N)),
+            (ExportLevel, Binding::key(KeyCode::Tab)),
+            (SelectAll, Binding::control(KeyCode::A)),
+            (Copy, Binding::control(KeyCode::C)),
+            (Paste, Binding::control(KeyCode::V)),
+            (PasteAsNewPart, Binding::control(KeyCode::B)),
+            (ToggleControlsScreen, Binding::key(KeyCode::F1)),
+            (ToggleAudioScreen, Binding::key(KeyCode::F2)),
+            (ToggleGraphicsScreen, Binding::key(KeyCode::F3)),
+            (ToggleMotionOverlay, Binding::key(KeyCode::O)),
+            (ImproveSolution, Binding::key(KeyCode::I)),
+            (CopySolutionCode, Binding::control(KeyCode::E)),
+            (PasteSolutionCode, Binding::control(KeyCode::I)),
+        ])
+    }
+}
+
+impl Bindings {
+    /// Starts from the default bindings and applies whatever the controls file
+    /// overrides. Writes the file out if it does not exist yet so there is
+    /// something to edit.
+    pub fn load() -> Self {
+        let mut bindings = Self::default();
+        let entries = match load_config(CONTROLS_FILE) {
+            Some(entries) => entries,
+            None => {
+                bindings.save();
+                return bindings;
+            }
+        };
+        for (name, value) in entries {
+            let binding = match Binding::parse(&value) {
+                Some(binding) => binding,
+                None => {
+                    println!("Unknown key \"{}\" in {}", value, CONTROLS_FILE);
+                    continue;
+                }
+            };
+            match bindings
+                .0
+                .iter_mut()
+                .find(|(action, _)| action.name() == name)
+            {
+                Some((_, existing)) => *existing = binding,
+                None => println!("Unknown action \"{}\" in {}", name, CONTROLS_FILE),
+            }
+        }
+        for (a, b) in bindings.conflicts() {
+            println!(
+                "Warning: {} and {} are both bound to {}",
+                a.name(),
+                b.name(),
+                bindings.get(a).unwrap().describe()
+            );
+        }
+        bindings
+    }
+
+    pub fn save(&self) {
+        let entries: Vec<_> = self
+            .0
+            .iter()
+            .map(|(action, binding)| (action.name(), binding.describe()))
+            .collect();
+        save_config(CONTROLS_FILE, &entries);
+    }
+
+    pub fn get(&self, action: Action) -> Option<Binding> {
+        self.0
+            .iter()
+            .find(|(candidate, _)| *candidate == action)
+            .map(|(_, binding)| *binding)
+    }
+
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        for (candidate, existing) in &mut self.0 {
+            if *candidate == action {
+                *existing = binding;
+            }
+        }
+    }
+
+    /// Pairs of actions that share a key while they could both be in use.
+    pub fn conflicts(&self) -> Vec<(Action, Action)> {
+        let mut conflicts = Vec::new();
+        for (index, &(a, a_binding)) in self.0.iter().enumerate() {
+            for &(b, b_binding) in &self.0[index + 1..] {
+                let contexts = (a.context(), b.context());
+                let exclusive = contexts == (ActionContext::Camera, ActionContext::Placing)
+                    || contexts == (ActionContext::Placing, ActionContext::Camera);
+                if a_binding == b_binding && !exclusive {
+                    conflicts.push((a, b));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ActionEvent {
+    pub action: Action,
+    pub pressed: bool,
+}
+
+/// Turns key presses into actions. Nothing but key releases gets through while
+/// a screen is open, so that the level does not react to keys meant for it.
+fn keyboard_action_system(
+    mut key_events: EventReader<KeyboardInput>,
+    mut actions: EventWriter<ActionEvent>,
+    bindings: Res<Bindings>,
+    open_screen: Res<OpenScreen>,
+    mut holding_control: Local<bool>,
+) {
+    for event in key_events.iter() {
+        let key = match event.key_code {
+            Some(key) => key,
+            None => continue,
+        };
+        let pressed = event.state == ButtonState::Pressed;
+        if key == KeyCode::LControl || key == KeyCode::RControl {
+            *holding_control = pressed;
+        }
+        if pressed && open_screen.is_open() {
+            continue;
+        }
+        for &(action, binding) in &bindings.0 {
+            if binding.key == key && (!pressed || binding.control == *holding_control) {
+                actions.send(ActionEvent { action, pressed });
+            }
+        }
+    }
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load())
+            .add_event::<ActionEvent>()
+            .add_system_to_stage(CoreStage::First, keyboard_action_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_survive_saving() {
+        for &key in BINDABLE_KEYS {
+            for control in [false, true] {
+                let binding = Binding::new(key, control).unwrap();
+                assert_eq!(Binding::parse(&binding.describe()), Some(binding));
+            }
+        }
+        for (action, binding) in Bindings::default().0 {
+            assert_eq!(
+                Binding::parse(&binding.describe()),
+                Some(binding),
+                "{}",
+                action.name()
+            );
+        }
+    }
+
+    #[test]
+    fn unsaveable_keys_can_not_be_bound() {
+        assert_eq!(Binding::new(KeyCode::LShift, false), None);
+        assert_eq!(Binding::new(KeyCode::Numpad0, true), None);
+    }
+}
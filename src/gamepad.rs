@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+use bevy_mod_raycast::{RayCastMethod, RayCastSource, RaycastSystem};
+
+use crate::{
+    block::BlockRaycastSet,
+    controls::{Action, ActionEvent},
+    screens::OpenScreen,
+};
+
+/// How far the cursor moves per second with the stick pushed all the way, in
+/// pixels.
+const CURSOR_SPEED: f32 = 700.0;
+const DEAD_ZONE: f32 = 0.2;
+/// How far the right stick has to be pushed before the camera starts moving.
+const CAMERA_THRESHOLD: f32 = 0.5;
+
+pub const BUTTON_ACTIONS: &[(GamepadButtonType, Action)] = &[
+    (GamepadButtonType::South, Action::Click),
+    (GamepadButtonType::West, Action::RemoveBlock),
+    (GamepadButtonType::North, Action::ToggleSimulation),
+    (GamepadButtonType::East, Action::Back),
+    (GamepadButtonType::LeftTrigger, Action::PreviousBlockKind),
+    (GamepadButtonType::RightTrigger, Action::NextBlockKind),
+    (GamepadButtonType::LeftTrigger2, Action::PreviousFacing),
+    (GamepadButtonType::RightTrigger2, Action::NextFacing),
+    (GamepadButtonType::DPadUp, Action::NextPart),
+    (GamepadButtonType::DPadDown, Action::PreviousPart),
+    (GamepadButtonType::Start, Action::ConfirmStart),
+    (GamepadButtonType::Select, Action::ToggleControlsScreen),
+];
+
+/// A cursor steered with the left stick. It takes over from the mouse as soon
+/// as the stick is moved and hands control back once the mouse moves.
+#[derive(Default)]
+pub struct VirtualCursor {
+    pub position: Vec2,
+    pub active: bool,
+    indicator: Option<Entity>,
+}
+
+impl VirtualCursor {
+    /// Where the player is pointing, in window coordinates.
+    pub fn cursor_position(&self, window: &Window) -> Option<Vec2> {
+        if self.active {
+            Some(self.position)
+        } else {
+            window.cursor_position()
+        }
+    }
+}
+
+fn gamepad_system(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    mut open_screen: ResMut<OpenScreen>,
+    mut cursor: ResMut<VirtualCursor>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut sources: Query<&mut RayCastSource<BlockRaycastSet>>,
+    mut actions: EventWriter<ActionEvent>,
+    mut camera_directions: Local<[bool; 4]>,
+) {
+    if cursor_moved.iter().count() > 0 {
+        cursor.active = false;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let mut cursor_stick = Vec2::ZERO;
+    let mut camera_stick = Vec2::ZERO;
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(*gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        cursor_stick += Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        camera_stick += Vec2::new(
+            axis(GamepadAxisType::RightStickX),
+            axis(GamepadAxisType::RightStickY),
+        );
+        for &(button_type, action) in BUTTON_ACTIONS {
+            let button = GamepadButton::new(*gamepad, button_type);
+            // Only the buttons for leaving a screen do anything while one is
+            // open, other presses would go to the level underneath.
+            if buttons.just_pressed(button) {
+                if !open_screen.is_open() || action == Action::ToggleControlsScreen {
+                    actions.send(ActionEvent {
+                        action,
+                        pressed: true,
+                    });
+                } else if action == Action::Back {
+                    // Like Escape on the screens, without going on to leave
+                    // the level as well.
+                    open_screen.close();
+                }
+            }
+            if buttons.just_released(button) {
+                actions.send(ActionEvent {
+                    action,
+                    pressed: false,
+                });
+            }
+        }
+    }
+
+    let size = Vec2::new(window.width(), window.height());
+    if cursor_stick.length() > DEAD_ZONE {
+        if !cursor.active {
+            cursor.position = window.cursor_position().unwrap_or(size / 2.0);
+            cursor.active = true;
+        }
+        cursor.position += cursor_stick * CURSOR_SPEED * time.delta_seconds();
+        cursor.position = cursor.position.clamp(Vec2::ZERO, size);
+    }
+    if cursor.active {
+        for mut source in sources.iter_mut() {
+            source.cast_method = RayCastMethod::Screenspace(cursor.position);
+        }
+    }
+
+    // Same order as the camera keys: forward, left, back, right.
+    let wanted = [
+        camera_stick.y > CAMERA_THRESHOLD,
+        camera_stick.x < -CAMERA_THRESHOLD,
+        camera_stick.y < -CAMERA_THRESHOLD,
+        camera_stick.x > CAMERA_THRESHOLD,
+    ];
+    for (index, &pressed) in wanted.iter().enumerate() {
+        if camera_directions[index] != pressed {
+            camera_directions[index] = pressed;
+            actions.send(ActionEvent {
+                action: Action::MoveCamera(index),
+                pressed,
+            });
+        }
+    }
+}
+
+fn setup_cursor_indicator(mut commands: Commands, mut cursor: ResMut<VirtualCursor>) {
+    let indicator = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size {
+                    width: Val::Px(12.0),
+                    height: Val::Px(12.0),
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(Color::hex("E9C46A").unwrap()),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .id();
+    cursor.indicator = Some(indicator);
+}
+
+fn update_cursor_indicator(
+    cursor: Res<VirtualCursor>,
+    mut indicators: Query<(&mut Style, &mut Visibility)>,
+) {
+    let indicator = match cursor.indicator {
+        Some(indicator) => indicator,
+        None => return,
+    };
+    if let Ok((mut style, mut visibility)) = indicators.get_mut(indicator) {
+        visibility.is_visible = cursor.active;
+        style.position.left = Val::Px(cursor.position.x - 6.0);
+        style.position.bottom = Val::Px(cursor.position.y - 6.0);
+    }
+}
+
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VirtualCursor>()
+            .add_startup_system(setup_cursor_indicator)
+            .add_system_to_stage(
+                CoreStage::First,
+                gamepad_system.before(RaycastSystem::BuildRays::<BlockRaycastSet>),
+            )
+            .add_system(update_cursor_indicator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        ecs::event::Events,
+        input::{
+            gamepad::{GamepadEventRaw, GamepadEventType},
+            InputPlugin,
+        },
+        window::{WindowDescriptor, WindowId, WindowPlugin},
+    };
+    use raw_window_handle::{RawWindowHandle, WebHandle};
+
+    use super::*;
+    use crate::screens::Screen;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(WindowPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_event::<ActionEvent>()
+            .init_resource::<OpenScreen>()
+            .add_plugin(GamepadPlugin);
+        let window = Window::new(
+            WindowId::primary(),
+            &WindowDescriptor::default(),
+            800,
+            600,
+            1.0,
+            None,
+            RawWindowHandle::Web(WebHandle::empty()),
+        );
+        app.world.resource_mut::<Windows>().add(window);
+        send(&mut app, GamepadEventType::Connected);
+        app
+    }
+
+    fn send(app: &mut App, event_type: GamepadEventType) {
+        app.world
+            .send_event(GamepadEventRaw::new(Gamepad::new(0), event_type));
+        app.update();
+    }
+
+    fn actions(app: &mut App) -> Vec<(Action, bool)> {
+        app.world
+            .resource_mut::<Events<ActionEvent>>()
+            .drain()
+            .map(|event| (event.action, event.pressed))
+            .collect()
+    }
+
+    #[test]
+    fn left_stick_moves_the_cursor() {
+        let mut app = app();
+        assert!(!app.world.resource::<VirtualCursor>().active);
+        send(
+            &mut app,
+            GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, 1.0),
+        );
+        for _ in 0..3 {
+            std::thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+        let cursor = app.world.resource::<VirtualCursor>();
+        assert!(cursor.active);
+        assert!(cursor.position.x > 400.0);
+        assert_eq!(cursor.position.y, 300.0);
+    }
+
+    #[test]
+    fn buttons_send_their_actions() {
+        let mut app = app();
+        for &(button, action) in BUTTON_ACTIONS {
+            actions(&mut app);
+            send(&mut app, GamepadEventType::ButtonChanged(button, 1.0));
+            app.update();
+            assert!(actions(&mut app).contains(&(action, true)), "{:?}", button);
+            send(&mut app, GamepadEventType::ButtonChanged(button, 0.0));
+            app.update();
+            assert!(actions(&mut app).contains(&(action, false)), "{:?}", button);
+        }
+    }
+
+    #[test]
+    fn open_screens_only_get_releases() {
+        let mut app = app();
+        app.world
+            .resource_mut::<OpenScreen>()
+            .toggle(Screen::Controls);
+        let (button, action) = BUTTON_ACTIONS[0];
+        actions(&mut app);
+        send(&mut app, GamepadEventType::ButtonChanged(button, 1.0));
+        app.update();
+        assert_eq!(actions(&mut app), vec![]);
+        send(&mut app, GamepadEventType::ButtonChanged(button, 0.0));
+        app.update();
+        assert_eq!(actions(&mut app), vec![(action, false)]);
+    }
+
+    #[test]
+    fn open_screens_can_be_left_with_the_gamepad() {
+        let mut app = app();
+        app.world
+            .resource_mut::<OpenScreen>()
+            .toggle(Screen::Controls);
+        actions(&mut app);
+        send(
+            &mut app,
+            GamepadEventType::ButtonChanged(GamepadButtonType::East, 1.0),
+        );
+        app.update();
+        assert!(!app.world.resource::<OpenScreen>().is_open());
+        assert_eq!(actions(&mut app), vec![]);
+
+        app.world
+            .resource_mut::<OpenScreen>()
+            .toggle(Screen::Controls);
+        send(
+            &mut app,
+            GamepadEventType::ButtonChanged(GamepadButtonType::Select, 1.0),
+        );
+        app.update();
+        assert_eq!(
+            actions(&mut app),
+            vec![(Action::ToggleControlsScreen, true)]
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Practice levels made from a seed. The same seed always makes the same level,
+//! on any platform, so seeds can be shared. That is why this uses `ChaCha8Rng`
+//! rather than `StdRng`, whose algorithm may change between versions of rand.
+//!
+//! Inputs are small random shapes, sometimes a second one that is the first
+//! turned around. The output is a few of them standing next to each other on
+//! the floor, so it can be made by pulling inputs into place and welding them,
+//! and every level is checked with `buildable` before it is handed out.
+
+use bevy::utils::HashSet;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    block::{Block, BlockFacing, BlockKind},
+    level_format::{Level, Section},
+    structure::Structure,
+    world::Position,
+};
+
+/// The floor goes from -FLOOR_RADIUS to FLOOR_RADIUS on x and y, the same as
+/// the hand made levels.
+const FLOOR_RADIUS: i32 = 20;
+const MAX_SHAPE_BLOCKS: usize = 4;
+
+fn shape_block(position: Position) -> Block {
+    Block {
+        kind: BlockKind::DecoStructure2,
+        facing: BlockFacing::Pz,
+        position,
+    }
+}
+
+fn translated(structure: &Structure, offset: Position) -> Structure {
+    let mut structure = structure.clone();
+    structure.translate(offset);
+    structure
+}
+
+fn min_corner(structure: &Structure) -> Position {
+    structure
+        .blocks
+        .iter()
+        .fold((i32::MAX, i32::MAX, i32::MAX), |min, block| {
+            let (x, y, z) = block.position;
+            (min.0.min(x), min.1.min(y), min.2.min(z))
+        })
+}
+
+fn max_corner(structure: &Structure) -> Position {
+    structure
+        .blocks
+        .iter()
+        .fold((i32::MIN, i32::MIN, i32::MIN), |max, block| {
+            let (x, y, z) = block.position;
+            (max.0.max(x), max.1.max(y), max.2.max(z))
+        })
+}
+
+/// Moves the structure so that its bounding box starts at the origin.
+fn to_origin(structure: &Structure) -> Structure {
+    let (x, y, z) = min_corner(structure);
+    translated(structure, (-x, -y, -z))
+}
+
+fn add(a: Position, b: Position) -> Position {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+/// Grows a shape one block at a time. Blocks only go up on top of other
+/// blocks, so the shape never has to float.
+fn random_shape(rng: &mut ChaCha8Rng) -> Structure {
+    let size = rng.gen_range(2..=MAX_SHAPE_BLOCKS);
+    let mut structure = Structure {
+        blocks: vec![shape_block((0, 0, 0))],
+    };
+    while structure.blocks.len() < size {
+        let from = structure.blocks[rng.gen_range(0..structure.blocks.len())].position;
+        let direction = match rng.gen_range(0..5) {
+            0 => BlockFacing::Px,
+            1 => BlockFacing::Py,
+            2 => BlockFacing::Nx,
+            3 => BlockFacing::Ny,
+            _ if from.2 == 0 => BlockFacing::Pz,
+            _ => continue,
+        };
+        let position = add(from, direction.offset());
+        if structure.get_block_at(position).is_none() {
+            structure.blocks.push(shape_block(position));
+        }
+    }
+    to_origin(&structure)
+}
+
+/// Puts copies of the shapes next to each other on the floor, using every
+/// shape at least once. `None` if a copy could not be fitted in.
+fn random_output(rng: &mut ChaCha8Rng, shapes: &[Structure]) -> Option<Structure> {
+    let mut order: Vec<usize> = (0..shapes.len()).collect();
+    let copies = rng.gen_range(2..=3).max(shapes.len());
+    while order.len() < copies {
+        order.push(rng.gen_range(0..shapes.len()));
+    }
+    let mut output = shapes[order[0]].clone();
+    for &shape in &order[1..] {
+        let shape = &shapes[shape];
+        let fitted = (0..50).find_map(|_| {
+            let touching = output.blocks[rng.gen_range(0..output.blocks.len())].position;
+            let direction = BlockFacing::all()[rng.gen_range(0..6)];
+            let anchor = shape.blocks[rng.gen_range(0..shape.blocks.len())].position;
+            let target = add(touching, direction.offset());
+            let copy = translated(
+                shape,
+                (
+                    target.0 - anchor.0,
+                    target.1 - anchor.1,
+                    target.2 - anchor.2,
+                ),
+            );
+            let fits = min_corner(&copy).2 == 0
+                && copy
+                    .blocks
+                    .iter()
+                    .all(|block| output.get_block_at(block.position).is_none());
+            fits.then(|| copy)
+        })?;
+        output.blocks.extend(fitted.blocks);
+    }
+    Some(to_origin(&output))
+}
+
+fn connected(structure: &Structure) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<Position> = structure
+        .blocks
+        .first()
+        .map(|x| x.position)
+        .into_iter()
+        .collect();
+    while let Some(position) = stack.pop() {
+        if !seen.insert(position) {
+            continue;
+        }
+        for direction in BlockFacing::all() {
+            let next = add(position, direction.offset());
+            if structure.get_block_at(next).is_some() {
+                stack.push(next);
+            }
+        }
+    }
+    seen.len() == structure.blocks.len()
+}
+
+/// Whether `remaining` can be split up into moved copies of the inputs. With a
+/// `floor`, the lowest blocks of every copy have to be at that height.
+fn covers(inputs: &[Structure], remaining: &mut Vec<Block>, floor: Option<i32>) -> bool {
+    let first = match remaining.iter().min_by_key(|block| {
+        let (x, y, z) = block.position;
+        (z, y, x)
+    }) {
+        Some(&first) => first,
+        None => return true,
+    };
+    for input in inputs {
+        for anchor in &input.blocks {
+            if anchor.kind != first.kind || anchor.facing != first.facing {
+                continue;
+            }
+            let (x, y, z) = anchor.position;
+            let copy = translated(
+                input,
+                (
+                    first.position.0 - x,
+                    first.position.1 - y,
+                    first.position.2 - z,
+                ),
+            );
+            if floor.map_or(false, |floor| min_corner(&copy).2 != floor)
+                || !copy.blocks.iter().all(|block| remaining.contains(block))
+            {
+                continue;
+            }
+            let before = remaining.clone();
+            remaining.retain(|block| !copy.blocks.contains(block));
+            if covers(inputs, remaining, floor) {
+                return true;
+            }
+            *remaining = before;
+        }
+    }
+    false
+}
+
+/// Whether the output can be built by sliding copies of the inputs across the
+/// floor and welding them into one part. Parts can not be turned, and a part
+/// with nothing under it falls before it can be welded, so every copy has to
+/// stand on the floor on its own. Lifting a part on top of another takes a beam
+/// pulling it up the whole time, which is left to the hand made levels.
+///
+/// This only looks at the shapes, not at whether a machine fits around them.
+pub fn buildable(inputs: &[Structure], output: &Structure) -> bool {
+    !output.blocks.is_empty()
+        && connected(output)
+        && covers(inputs, &mut output.blocks.clone(), Some(0))
+}
+
+/// Whether the output can be split up into moved copies of the inputs.
+pub fn made_of(inputs: &[Structure], output: &Structure) -> bool {
+    covers(inputs, &mut output.blocks.clone(), None)
+}
+
+/// Lays the inputs out in a row in front of the output and builds the floor
+/// under them.
+fn layout(inputs: Vec<Structure>, output: Structure) -> Level {
+    let width = |structure: &Structure| max_corner(structure).0 - min_corner(structure).0 + 1;
+    let total: i32 = inputs.iter().map(|input| width(input) + 2).sum::<i32>() - 2;
+    let mut x = -total / 2;
+    let mut placed = Vec::new();
+    for input in inputs {
+        let next = x + width(&input) + 2;
+        placed.push(translated(&input, (x, -6, 0)));
+        x = next;
+    }
+    let output = translated(&output, (-width(&output) / 2, 4, 0));
+
+    let under = |structures: &[Structure], x, y| {
+        structures.iter().any(|structure| {
+            structure
+                .blocks
+                .iter()
+                .any(|block| block.position.0 == x && block.position.1 == y)
+        })
+    };
+    let mut floor = Structure { blocks: Vec::new() };
+    for y in -FLOOR_RADIUS..=FLOOR_RADIUS {
+        for x in -FLOOR_RADIUS..=FLOOR_RADIUS {
+            let kind = if under(&placed, x, y) {
+                BlockKind::DecoStructureInput
+            } else if under(std::slice::from_ref(&output), x, y) {
+                BlockKind::DecoStructureOutput
+            } else {
+                BlockKind::DecoStructure
+            };
+            floor.blocks.push(Block {
+                kind,
+                facing: BlockFacing::Pz,
+                position: (x, y, -1),
+            });
+        }
+    }
+    let mut level = vec![(Section::Floor, floor)];
+    level.extend(placed.into_iter().map(|input| (Section::Input, input)));
+    level.push((Section::Output, output));
+    level
+}
+
+/// Tries shapes until it finds a level that can be solved.
+pub fn generate(seed: u64) -> Level {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    loop {
+        let first = random_shape(&mut rng);
+        let mut inputs = vec![first.clone()];
+        if rng.gen_bool(0.5) {
+            // Either a new shape or the first one turned a quarter.
+            let second = if rng.gen_bool(0.5) {
+                to_origin(&first.rotated(BlockFacing::Py))
+            } else {
+                random_shape(&mut rng)
+            };
+            if second != first {
+                inputs.push(second);
+            }
+        }
+        let output = match random_output(&mut rng, &inputs) {
+            Some(output) => output,
+            None => continue,
+        };
+        if buildable(&inputs, &output) {
+            return layout(inputs, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_format::write_level;
+
+    fn bar(blocks: &[Position]) -> Structure {
+        Structure {
+            blocks: blocks.iter().copied().map(shape_block).collect(),
+        }
+    }
+
+    #[test]
+    fn copies_have_to_stand_on_the_floor() {
+        let inputs = [bar(&[(0, 0, 0), (1, 0, 0)])];
+        let side_by_side = bar(&[(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 1, 0)]);
+        assert!(buildable(&inputs, &side_by_side));
+        let stacked = bar(&[(0, 0, 0), (1, 0, 0), (1, 0, 1), (2, 0, 1)]);
+        assert!(made_of(&inputs, &stacked));
+        assert!(!buildable(&inputs, &stacked));
+        let apart = bar(&[(0, 0, 0), (1, 0, 0), (3, 0, 0), (4, 0, 0)]);
+        assert!(!buildable(&inputs, &apart));
+    }
+
+    #[test]
+    fn generated_levels_are_buildable() {
+        for seed in 0..20 {
+            let level = generate(seed);
+            let pieces = |section| {
+                level
+                    .iter()
+                    .filter(move |(x, _)| *x == section)
+                    .map(|(_, structure)| structure.clone())
+            };
+            let inputs: Vec<_> = pieces(Section::Input).collect();
+            for output in pieces(Section::Output) {
+                assert!(buildable(&inputs, &output), "seed {}", seed);
+            }
+        }
+    }
+
+    /// If this has to change, seeds that players have shared make different
+    /// levels from now on.
+    #[test]
+    fn seeds_make_the_same_level_everywhere() {
+        let text = write_level(&generate(7));
+        let pieces = &text[text.find("\ninput\n").unwrap() + 1..];
+        assert_eq!(
+            pieces,
+            concat!(
+                "input\n",
+                "x4 -2 -6 0\n",
+                "x4 -2 -5 0\n",
+                "input\n",
+                "x4 1 -6 0 2\n",
+                "output\n",
+                "x4 0 4 0\n",
+                "x4 0 5 0\n",
+                "x4 -1 6 0 2\n",
+            )
+        );
+    }
+}
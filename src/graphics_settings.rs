@@ -0,0 +1,218 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+
+use crate::{
+    config::{load_config, save_config},
+    palette::{palettes, Palette},
+    setup::PostProcessMaterial,
+};
+
+const GRAPHICS_FILE: &str = "graphics.txt";
+
+const HOLOGRAM_TINTS: &[(&str, &str)] = &[
+    ("white", "FFFFFF"),
+    ("cyan", "A2F4FF"),
+    ("green", "B4FFB4"),
+    ("amber", "FFD59A"),
+    ("magenta", "FFB4F0"),
+];
+
+/// How the post-process pass draws holograms. The defaults match the look the
+/// shader originally had hard coded.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GraphicsSettings {
+    /// Scanlines per screen height.
+    pub scanline_density: f32,
+    /// How much darker the dark half of each scanline is.
+    pub scanline_dimming: f32,
+    pub brightness: f32,
+    /// How strongly holograms flicker, 0 turns it off.
+    pub flicker: f32,
+    /// Index into `HOLOGRAM_TINTS`.
+    pub hologram_tint: usize,
+    /// Index into `palettes()`.
+    pub palette: usize,
+    /// Puts shapes on input and output tiles so they do not rely on colour.
+    pub shape_markers: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            scanline_density: 100.0,
+            scanline_dimming: 0.3,
+            brightness: 0.8,
+            flicker: 0.0,
+            hologram_tint: 0,
+            palette: 0,
+            shape_markers: false,
+        }
+    }
+}
+
+pub const GRAPHICS_ROWS: usize = 7;
+
+impl GraphicsSettings {
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let entries = match load_config(GRAPHICS_FILE) {
+            Some(entries) => entries,
+            None => return settings,
+        };
+        for (name, value) in entries {
+            let ok = match name.as_str() {
+                "scanline_density" => value.parse().map(|x| settings.scanline_density = x).is_ok(),
+                "scanline_dimming" => value.parse().map(|x| settings.scanline_dimming = x).is_ok(),
+                "brightness" => value.parse().map(|x| settings.brightness = x).is_ok(),
+                "flicker" => value.parse().map(|x| settings.flicker = x).is_ok(),
+                "hologram_tint" => HOLOGRAM_TINTS
+                    .iter()
+                    .position(|(tint, _)| *tint == value)
+                    .map(|x| settings.hologram_tint = x)
+                    .is_some(),
+                "palette" => palettes()
+                    .iter()
+                    .position(|palette| palette.name == value)
+                    .map(|x| settings.palette = x)
+                    .is_some(),
+                "shape_markers" => value.parse().map(|x| settings.shape_markers = x).is_ok(),
+                _ => false,
+            };
+            if !ok {
+                println!("Ignoring \"{} = {}\" in {}", name, value, GRAPHICS_FILE);
+            }
+        }
+        // Goes through `adjust` so that out of range values get clamped.
+        for row in 0..GRAPHICS_ROWS {
+            settings.adjust(row, 0);
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        let entries = [
+            ("scanline_density", self.scanline_density.to_string()),
+            ("scanline_dimming", self.scanline_dimming.to_string()),
+            ("brightness", self.brightness.to_string()),
+            ("flicker", self.flicker.to_string()),
+            (
+                "hologram_tint",
+                HOLOGRAM_TINTS[self.hologram_tint].0.to_owned(),
+            ),
+            ("palette", self.palette().name.to_owned()),
+            ("shape_markers", self.shape_markers.to_string()),
+        ];
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
+        save_config(GRAPHICS_FILE, &entries);
+    }
+
+    /// Moves the setting shown on the given row of the graphics screen by a
+    /// number of steps.
+    pub fn adjust(&mut self, row: usize, steps: i32) {
+        // Rounded so that repeated steps do not drift away from round numbers.
+        let step = |value: f32, size: f32, min: f32, max: f32| {
+            ((value / size).round() + steps as f32).clamp(min / size, max / size) * size
+        };
+        match row {
+            0 => self.scanline_density = step(self.scanline_density, 10.0, 10.0, 400.0),
+            1 => self.scanline_dimming = step(self.scanline_dimming, 0.05, 0.0, 1.0),
+            2 => self.brightness = step(self.brightness, 0.05, 0.2, 2.0),
+            3 => self.flicker = step(self.flicker, 0.05, 0.0, 1.0),
+            4 => {
+                let count = HOLOGRAM_TINTS.len() as i32;
+                let index = (self.hologram_tint as i32 + steps).rem_euclid(count);
+                self.hologram_tint = index as usize;
+            }
+            5 => {
+                let count = palettes().len() as i32;
+                self.palette = (self.palette as i32 + steps).rem_euclid(count) as usize;
+            }
+            _ => {
+                if steps % 2 != 0 {
+                    self.shape_markers = !self.shape_markers;
+                }
+            }
+        }
+    }
+
+    /// Names and values for each row of the graphics screen.
+    pub fn rows(&self) -> [(&'static str, String); GRAPHICS_ROWS] {
+        [
+            ("Scanline density", format!("{}", self.scanline_density)),
+            ("Scanline dimming", format!("{:.2}", self.scanline_dimming)),
+            ("Brightness", format!("{:.2}", self.brightness)),
+            ("Flicker", format!("{:.2}", self.flicker)),
+            (
+                "Hologram tint",
+                HOLOGRAM_TINTS[self.hologram_tint].0.to_owned(),
+            ),
+            ("Palette", self.palette().name.to_owned()),
+            (
+                "Shape markers",
+                (if self.shape_markers { "on" } else { "off" }).to_owned(),
+            ),
+        ]
+    }
+
+    pub fn palette(&self) -> Palette {
+        palettes().into_iter().nth(self.palette).unwrap()
+    }
+
+    /// The tint picked on the graphics screen combined with the palette's.
+    pub fn hologram_tint(&self) -> Color {
+        let tint = Color::hex(HOLOGRAM_TINTS[self.hologram_tint].1).unwrap();
+        let palette = self.palette().hologram;
+        let tint = Vec4::from(tint.as_rgba_f32()) * Vec4::from(palette.as_rgba_f32());
+        Color::rgba(tint.x, tint.y, tint.z, tint.w)
+    }
+}
+
+/// Mirrors the `HologramSettings` struct in `hologram.wgsl`.
+#[derive(ShaderType, Clone, Default)]
+pub struct HologramUniform {
+    pub tint: Vec4,
+    pub scanline_density: f32,
+    pub scanline_dimming: f32,
+    pub brightness: f32,
+    pub flicker: f32,
+    pub time: f32,
+}
+
+impl HologramUniform {
+    pub fn new(settings: &GraphicsSettings, time: f32) -> Self {
+        Self {
+            tint: settings.hologram_tint().as_rgba_f32().into(),
+            scanline_density: settings.scanline_density,
+            scanline_dimming: settings.scanline_dimming,
+            brightness: settings.brightness,
+            flicker: settings.flicker,
+            time,
+        }
+    }
+}
+
+fn update_post_process_material(
+    settings: Res<GraphicsSettings>,
+    time: Res<Time>,
+    quads: Query<&Handle<PostProcessMaterial>>,
+    mut materials: ResMut<Assets<PostProcessMaterial>>,
+) {
+    // Wrapped so the flicker does not lose precision after running for hours.
+    let time = (time.seconds_since_startup() % 3600.0) as f32;
+    for handle in quads.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.settings = HologramUniform::new(&*settings, time);
+        }
+    }
+}
+
+pub struct GraphicsSettingsPlugin;
+
+impl Plugin for GraphicsSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GraphicsSettings::load())
+            .add_system(update_post_process_material);
+    }
+}
@@ -1,7 +1,11 @@
 mod base;
+mod clipboard;
 mod keys;
+mod motion_overlay;
 mod mouse;
+mod outliner;
 pub mod setup;
+mod split_warning;
 mod util;
 
 pub use base::*;
@@ -14,16 +18,30 @@ use bevy_mod_raycast::Intersection;
 
 pub use self::keys::exit_level;
 use self::{
+    clipboard::{
+        clear_selection_on_part_switch, copy_solution_code, paste_solution_code,
+        update_clipboard_keys,
+    },
     keys::{move_cameras, update_block_keys, update_directional_key},
+    motion_overlay::motion_overlay_system,
     mouse::handle_mouse,
+    outliner::{
+        follow_restored_parts, handle_outliner_click, make_outliner, part_appearance_system,
+    },
+    split_warning::{make_split_warning, request_begin_simulation, split_highlight_system},
 };
 use crate::{
-    block::{Block, BlockFacing, BlockKind, BlockRaycastSet},
+    audio_settings::AudioSettings,
+    block::{BlockKind, BlockRaycastSet},
+    controls::{Action, ActionEvent},
+    gamepad::VirtualCursor,
+    level_format::{parse_level, write_level, Section},
+    level_validator::validate_text,
     setup::LevelEntity,
     setup_menu::GlobalState,
     simulation::{self, make_input, make_output, SimulationState},
     structure::Structure,
-    world::{World, WorldSnapshot},
+    world::{Part, World, WorldDescription, WorldSnapshot},
     GameState, Sfx,
 };
 
@@ -35,6 +53,7 @@ pub fn interface_system(
     mut cursor: Query<(&mut Transform, &mut Visibility), (With<Cursor>, Without<Camera3d>)>,
     block_raycast_intersection: Query<(&Intersection<BlockRaycastSet>,)>,
     mut key_events: EventReader<KeyboardInput>,
+    mut action_events: EventReader<ActionEvent>,
     mut mouse_button_events: EventReader<MouseButtonInput>,
     mut state: ResMut<InterfaceState>,
     mut simulation_state: ResMut<SimulationState>,
@@ -44,10 +63,18 @@ pub fn interface_system(
     time: Res<Time>,
     mut global_state: ResMut<GlobalState>,
     windows: Res<Windows>,
+    virtual_cursor: Res<VirtualCursor>,
     sfx: Res<Sfx>,
     audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
 ) {
-    for event in key_events.iter() {
+    let mut clicked = false;
+    let mut remove_clicked = false;
+    for event in action_events.iter() {
+        if event.pressed {
+            clicked |= event.action == Action::Click;
+            remove_clicked |= event.action == Action::RemoveBlock;
+        }
         update_directional_key(
             &mut commands,
             event,
@@ -61,16 +88,29 @@ pub fn interface_system(
             },
         );
         update_block_keys(event, &mut *state, &*simulation_state);
+        update_clipboard_keys(
+            &mut commands,
+            event,
+            &mut *state,
+            &*simulation_state,
+            &*world,
+            &*assets,
+        );
+    }
+    for event in key_events.iter() {
         if event.key_code == Some(KeyCode::LShift) || event.key_code == Some(KeyCode::RShift) {
             if event.state == ButtonState::Pressed {
                 state.holding_shift = true;
             } else {
                 state.block_to_place = None;
+                state.pasting = None;
                 state.holding_shift = false;
             }
         }
+        if event.key_code == Some(KeyCode::LControl) || event.key_code == Some(KeyCode::RControl) {
+            state.holding_control = event.state == ButtonState::Pressed;
+        }
     }
-    let mut clicked = false;
     for event in mouse_button_events.iter() {
         if event.button == MouseButton::Left && event.state == ButtonState::Pressed {
             clicked = true;
@@ -85,6 +125,7 @@ pub fn interface_system(
         &mut *simulation_state,
         &mut *global_state,
         &*windows,
+        &*virtual_cursor,
         clicked,
         &*assets,
     );
@@ -95,24 +136,53 @@ pub fn interface_system(
         block_raycast_intersection,
         &mut commands,
         clicked && !ui_captured_click,
+        remove_clicked,
         &mut *world,
         &*assets,
         &*sfx,
         &*audio,
+        &*audio_settings,
     );
 
     move_cameras(cameras.iter_mut(), state.movement_keys, &*time);
+    if let Some((_, seconds)) = &mut state.notice {
+        *seconds -= time.delta_seconds();
+        if *seconds <= 0.0 {
+            state.notice = None;
+        }
+    }
     delete_ui(&mut commands, state.ui_root);
+    let parts = if simulation_state.is_started() {
+        world_snapshot.0.parts()
+    } else {
+        world.parts()
+    };
     let new_ui_root = make_ui(
         &mut commands,
         &*assets,
         &*state,
         &*simulation_state,
         &*global_state,
+        parts,
     );
     state.ui_root = new_ui_root;
 }
 
+fn stop_simulation(
+    world: &mut World,
+    snapshot: &WorldSnapshot,
+    simulation_state: &mut SimulationState,
+    state: &mut InterfaceState,
+    commands: &mut Commands,
+    assets: &AssetServer,
+) {
+    let was_started = simulation_state.is_started();
+    simulation::end_simulation(world, snapshot, simulation_state, commands, assets);
+    if was_started {
+        follow_restored_parts(state, &snapshot.0, world);
+    }
+}
+
 fn handle_ui(
     commands: &mut Commands,
     world: &mut World,
@@ -122,15 +192,24 @@ fn handle_ui(
     simulation_state: &mut SimulationState,
     global_state: &mut GlobalState,
     windows: &Windows,
+    virtual_cursor: &VirtualCursor,
     clicked: bool,
     assets: &AssetServer,
 ) -> bool {
     let window = windows.primary();
     let height = window.height();
-    let cursor_pos = window
-        .cursor_position()
+    let cursor_pos = virtual_cursor
+        .cursor_position(window)
         .map(|x| x * 720.0 / height)
         .unwrap_or(Vec2::new(-1000.0, -1000.0));
+    let parts = if is_started {
+        world_snapshot.0.parts()
+    } else {
+        world.parts()
+    };
+    if clicked && handle_outliner_click(state, parts, cursor_pos) {
+        return true;
+    }
     let cep = &mut state.currently_editing_part;
     if !clicked {
         false
@@ -178,33 +257,46 @@ fn handle_ui(
         state.block_to_place = Some(BlockKind::WelderBeamSource);
         true
     } else if cursor_pos.clamp((33.0, 100.0).into(), (98.0, 148.0).into()) == cursor_pos {
-        simulation::end_simulation(world, world_snapshot, simulation_state, commands, assets);
+        stop_simulation(
+            world,
+            world_snapshot,
+            simulation_state,
+            state,
+            commands,
+            assets,
+        );
         true
     } else if cursor_pos.clamp((98.0, 100.0).into(), (157.0, 148.0).into()) == cursor_pos {
-        simulation::begin_simulation(
+        request_begin_simulation(
             world,
             world_snapshot,
             simulation_state,
+            state,
+            global_state,
             0.3,
             commands,
             assets,
         );
         true
     } else if cursor_pos.clamp((157.0, 100.0).into(), (215.0, 148.0).into()) == cursor_pos {
-        simulation::begin_simulation(
+        request_begin_simulation(
             world,
             world_snapshot,
             simulation_state,
+            state,
+            global_state,
             1.0,
             commands,
             assets,
         );
         true
     } else if cursor_pos.clamp((215.0, 100.0).into(), (275.0, 148.0).into()) == cursor_pos {
-        simulation::begin_simulation(
+        request_begin_simulation(
             world,
             world_snapshot,
             simulation_state,
+            state,
+            global_state,
             3.0,
             commands,
             assets,
@@ -217,33 +309,77 @@ fn handle_ui(
 
 pub fn simulation_interface_system(
     mut commands: Commands,
-    mut key_events: EventReader<KeyboardInput>,
+    mut action_events: EventReader<ActionEvent>,
     mut simulation_state: ResMut<SimulationState>,
     mut world: ResMut<World>,
     mut snapshot: ResMut<WorldSnapshot>,
     assets: Res<AssetServer>,
-    interface_state: Res<InterfaceState>,
+    mut interface_state: ResMut<InterfaceState>,
+    mut global_state: ResMut<GlobalState>,
 ) {
-    for event in key_events.iter() {
-        if event.key_code == Some(KeyCode::Tab) && event.state == ButtonState::Pressed {
-            println!("{}", export_level(&*world, interface_state.first_user_part));
+    for event in action_events.iter() {
+        if !event.pressed {
+            continue;
+        }
+        if event.action == Action::ExportLevel {
+            let level = export_level(&*world, interface_state.first_user_part);
+            println!("{}", level);
+            for problem in validate_text(&level) {
+                println!("{}", problem);
+            }
+            #[cfg(feature = "serde")]
+            WorldDescription::from_world(&*world, interface_state.first_user_part)
+                .save(&format!("level{}", global_state.current_level));
+        }
+        if event.action == Action::CopySolutionCode {
+            copy_solution_code(&mut *interface_state, &*world, global_state.current_level);
         }
-        if event.key_code == Some(KeyCode::Space) && event.state == ButtonState::Pressed {
+        if event.action == Action::PasteSolutionCode && !simulation_state.is_started() {
+            paste_solution_code(
+                &mut commands,
+                &mut *interface_state,
+                &mut *world,
+                global_state.current_level,
+                &*assets,
+            );
+        }
+        if let Some(speed) = interface_state.pending_start {
+            if event.action == Action::StopAskingAboutSplits {
+                global_state.confirm_splits = false;
+            }
+            if event.action == Action::ConfirmStart || event.action == Action::StopAskingAboutSplits
+            {
+                request_begin_simulation(
+                    &mut *world,
+                    &mut *snapshot,
+                    &mut *simulation_state,
+                    &mut *interface_state,
+                    &*global_state,
+                    speed,
+                    &mut commands,
+                    &*assets,
+                );
+            }
+        }
+        if event.action == Action::ToggleSimulation {
             if EDITING {
                 // export_level(&*world);
             } else if simulation_state.is_started() {
-                simulation::end_simulation(
+                stop_simulation(
                     &mut *world,
-                    &mut *snapshot,
+                    &*snapshot,
                     &mut *simulation_state,
+                    &mut *interface_state,
                     &mut commands,
                     &*assets,
                 );
             } else {
-                simulation::begin_simulation(
+                request_begin_simulation(
                     &mut *world,
                     &mut *snapshot,
                     &mut *simulation_state,
+                    &mut *interface_state,
+                    &*global_state,
                     1.0,
                     &mut commands,
                     &*assets,
@@ -253,30 +389,6 @@ pub fn simulation_interface_system(
     }
 }
 
-fn export_block(block: &Block) -> String {
-    let c = match block.kind {
-        BlockKind::DecoStructure => '#',
-        BlockKind::DecoStructure2 => 'x',
-        BlockKind::DecoStructureInput => 'i',
-        BlockKind::DecoStructureOutput => 'o',
-        BlockKind::Structure => 's',
-        BlockKind::Activator => 'a',
-        BlockKind::TractorBeamSource => 't',
-        BlockKind::WelderBeamSource => 'w',
-        BlockKind::LaserSource => 'l',
-        BlockKind::LaserSensor => 'n',
-    };
-    let f = match block.facing {
-        BlockFacing::Px => '0',
-        BlockFacing::Py => '1',
-        BlockFacing::Nx => '2',
-        BlockFacing::Ny => '3',
-        BlockFacing::Pz => '4',
-        BlockFacing::Nz => '5',
-    };
-    format!("{}{}", c, f)
-}
-
 #[must_use]
 pub fn import_level(
     input: &str,
@@ -284,132 +396,36 @@ pub fn import_level(
     commands: &mut Commands,
     assets: &AssetServer,
 ) -> usize {
-    let mut lines = input.lines();
-    let mut start = lines.next().unwrap().split(" ");
-    let min_x: i32 = start.next().unwrap().parse().unwrap();
-    let min_y: i32 = start.next().unwrap().parse().unwrap();
-    let min_z: i32 = start.next().unwrap().parse().unwrap();
-    assert_eq!(lines.next(), Some("floor"));
-    let mut mode = "floor";
-    let mut current_structure = Structure { blocks: Vec::new() };
+    let level = parse_level(input).unwrap_or_else(|error| panic!("Bad level: {}", error));
     let mut first_user_part = 1;
-    let mut create_structure = move |mode, structure| match mode {
-        "floor" => world.add_part(structure, commands, assets),
-        "input" => make_input(structure, world, commands, assets),
-        "output" => make_output(structure, world, commands, assets),
-        "part" => world.add_part(structure, commands, assets),
-        _ => panic!(),
-    };
-    let mut position = (min_x - 1, min_y, min_z);
-    for line in lines {
-        if line == "input" || line == "output" || line == "part" {
-            if line != "part" {
-                first_user_part += 1;
-            }
-            create_structure(mode, current_structure);
-            current_structure = Structure { blocks: Vec::new() };
-            mode = line;
-            position = (min_x - 1, min_y, min_z);
-        } else if line == "" {
-            position.1 = min_y;
-            position.2 += 1;
-        } else {
-            for pair in line.chars().collect::<Vec<_>>().chunks(2) {
-                position.0 += 1;
-                if let &[c, f] = pair {
-                    let kind = match c {
-                        '.' => continue,
-                        '#' => BlockKind::DecoStructure,
-                        'x' => BlockKind::DecoStructure2,
-                        'i' => BlockKind::DecoStructureInput,
-                        'o' => BlockKind::DecoStructureOutput,
-                        's' => BlockKind::Structure,
-                        'a' => BlockKind::Activator,
-                        't' => BlockKind::TractorBeamSource,
-                        'w' => BlockKind::WelderBeamSource,
-                        'l' => BlockKind::LaserSource,
-                        'n' => BlockKind::LaserSensor,
-                        other => panic!("{}", other),
-                    };
-                    let facing = match f {
-                        '0' => BlockFacing::Px,
-                        '1' => BlockFacing::Py,
-                        '2' => BlockFacing::Nx,
-                        '3' => BlockFacing::Ny,
-                        '4' => BlockFacing::Pz,
-                        '5' => BlockFacing::Nz,
-                        _ => panic!(),
-                    };
-                    current_structure.blocks.push(Block {
-                        kind,
-                        facing,
-                        position,
-                    });
-                } else {
-                    panic!()
-                }
-            }
-            position.0 = min_x - 1;
-            position.1 += 1;
+    for (section, structure) in level {
+        match section {
+            Section::Floor | Section::Part => world.add_part(structure, commands, assets),
+            Section::Input => make_input(structure, world, commands, assets),
+            Section::Output => make_output(structure, world, commands, assets),
+        }
+        if section == Section::Input || section == Section::Output {
+            first_user_part += 1;
         }
     }
-    create_structure(mode, current_structure);
     first_user_part
 }
 
 fn export_level(world: &World, first_user_part: usize) -> String {
-    let mut min = (i32::MAX, i32::MAX, i32::MAX);
-    let mut max = (i32::MIN, i32::MIN, i32::MIN);
-    for part in world.parts() {
-        for block in &part.structure.blocks {
-            min.0 = min.0.min(block.position.0);
-            min.1 = min.1.min(block.position.1);
-            min.2 = min.2.min(block.position.2);
-            max.0 = max.0.max(block.position.0);
-            max.1 = max.1.max(block.position.1);
-            max.2 = max.2.max(block.position.2);
-        }
-    }
-    let mut output = String::new();
-    for (index, part) in world.parts().iter().enumerate() {
-        if index == 0 {
-            output.push_str(&format!("{} {} {}\n", min.0, min.1, min.2));
-            output.push_str("floor\n");
-        } else if index == first_user_part - 1 {
-            output.push_str("output\n");
-        } else if index < first_user_part {
-            output.push_str("input\n");
-        } else {
-            output.push_str("part\n");
-        }
-        for z in min.2..=max.2 {
-            for y in min.1..=max.1 {
-                for x in min.0..=max.0 {
-                    if let Some(block) = part.structure.get_block_at((x, y, z)) {
-                        output.push_str(&export_block(block));
-                    } else {
-                        output.push_str(".0");
-                    }
-                }
-                output.push_str("\n");
-            }
-            output.push_str("\n");
-        }
-    }
-    output
+    write_level(&WorldDescription::from_world(world, first_user_part).to_level())
 }
 
 pub fn switch_part_system(
     mut commands: Commands,
     mut world: ResMut<World>,
     mut state: ResMut<InterfaceState>,
-    mut key_events: EventReader<KeyboardInput>,
+    mut action_events: EventReader<ActionEvent>,
     assets: Res<AssetServer>,
 ) {
     let state = &mut *state;
     let cep = &mut state.currently_editing_part;
-    for event in key_events.iter() {
-        if event.key_code == Some(KeyCode::Equals) && event.state == ButtonState::Pressed {
+    for event in action_events.iter() {
+        if event.action == Action::NextPart && event.pressed {
             *cep += 1;
             if world.parts().len() <= *cep {
                 let s = Structure { blocks: Vec::new() };
@@ -419,7 +435,7 @@ pub fn switch_part_system(
                     world.add_part(s, &mut commands, &*assets);
                 }
             }
-        } else if event.key_code == Some(KeyCode::Minus) && event.state == ButtonState::Pressed {
+        } else if event.action == Action::PreviousPart && event.pressed {
             if EDITING {
                 if *cep > 0 {
                     *cep -= 1;
@@ -709,12 +725,54 @@ fn make_hint_box(
     root
 }
 
+fn make_notice(commands: &mut Commands, assets: &AssetServer, message: &str) -> Entity {
+    let root = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position: UiRect {
+                    top: Val::Percent(3.0),
+                    left: Val::Percent(30.0),
+                    ..Default::default()
+                },
+                size: Size {
+                    width: Val::Percent(40.0),
+                    height: Val::Auto,
+                },
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Val::Px(8.0)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+            ..Default::default()
+        })
+        .id();
+    let text = commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            text: Text::from_section(
+                message,
+                TextStyle {
+                    font: assets.load("RobotoSlab-Regular.ttf"),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+            ),
+            ..Default::default()
+        })
+        .id();
+    commands.entity(root).add_child(text);
+    root
+}
+
 pub fn make_ui(
     commands: &mut Commands,
     assets: &AssetServer,
     state: &InterfaceState,
     simulation_state: &SimulationState,
     global_state: &GlobalState,
+    parts: &[Part],
 ) -> Entity {
     let root = commands
         .spawn()
@@ -741,6 +799,14 @@ pub fn make_ui(
     commands.entity(root).add_child(hotbar);
     let hint_box = make_hint_box(commands, assets, state, global_state, simulation_state);
     commands.entity(root).add_child(hint_box);
+    let outliner = make_outliner(commands, assets, state, parts);
+    commands.entity(root).add_child(outliner);
+    let split_warning = make_split_warning(commands, assets, state);
+    commands.entity(root).add_child(split_warning);
+    if let Some((message, _)) = &state.notice {
+        let notice = make_notice(commands, assets, message);
+        commands.entity(root).add_child(notice);
+    }
     root
 }
 
@@ -758,7 +824,11 @@ impl Plugin for InterfacePlugin {
             SystemSet::on_update(GameState::Level)
                 .with_system(interface_system)
                 .with_system(simulation_interface_system)
-                .with_system(switch_part_system),
+                .with_system(switch_part_system)
+                .with_system(clear_selection_on_part_switch)
+                .with_system(part_appearance_system)
+                .with_system(split_highlight_system)
+                .with_system(motion_overlay_system),
         );
     }
 }
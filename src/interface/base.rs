@@ -1,6 +1,11 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 
-use crate::block::{BlockFacing, BlockKind};
+use super::motion_overlay::MotionArrow;
+use crate::{
+    block::{BlockFacing, BlockKind},
+    structure::Structure,
+    world::Position,
+};
 
 #[derive(Component)]
 pub struct Cursor;
@@ -9,6 +14,12 @@ pub enum InterfaceMode {
     Default,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PasteMode {
+    IntoCurrentPart,
+    AsNewPart,
+}
+
 pub struct InterfaceState {
     pub mode: InterfaceMode,
     pub movement_keys: [bool; 4],
@@ -17,6 +28,31 @@ pub struct InterfaceState {
     pub block_to_place: Option<BlockKind>,
     pub facing: BlockFacing,
     pub holding_shift: bool,
+    pub holding_control: bool,
+    pub selection: HashSet<Position>,
+    pub selection_markers: Vec<Entity>,
+    pub clipboard: Option<Structure>,
+    pub pasting: Option<PasteMode>,
+    /// How the clipboard is turned when pasted, `Px` keeps it as it was
+    /// copied.
+    pub paste_facing: BlockFacing,
+    pub paste_preview: Option<(Entity, BlockFacing)>,
+    /// Keyed on `Part::physical_instance`, which stays with a part when
+    /// other parts around it are merged, split or removed.
+    pub hidden_parts: HashSet<Entity>,
+    pub solo_part: Option<Entity>,
+    /// Set when the player asked to start the simulation but still has to
+    /// confirm that it is okay for some parts to split.
+    pub pending_start: Option<f32>,
+    pub split_warning: Vec<(usize, usize)>,
+    pub split_pieces: Vec<Structure>,
+    pub split_markers: Vec<Entity>,
+    /// A message for the player, mostly about copying and pasting solution
+    /// codes, and how many more seconds it is shown for.
+    pub notice: Option<(String, f32)>,
+    pub show_motion_overlay: bool,
+    pub motion_arrows: Vec<MotionArrow>,
+    pub motion_markers: Vec<Entity>,
     pub place_cursor: Entity,
     pub remove_cursor: Entity,
     pub ui_root: Entity,
@@ -0,0 +1,296 @@
+use bevy::prelude::*;
+
+use super::{InterfaceState, PasteMode};
+use crate::{
+    audio_settings::AudioSettings,
+    block::BlockFacing,
+    controls::{Action, ActionEvent},
+    setup::LevelEntity,
+    simulation::SimulationState,
+    solution_code::{self, copy_to_clipboard, paste_from_clipboard},
+    structure::{spawn_ghost_structure, Structure},
+    world::{Position, World},
+    Sfx,
+};
+
+pub(super) fn update_clipboard_keys(
+    commands: &mut Commands,
+    event: &ActionEvent,
+    state: &mut InterfaceState,
+    simulation_state: &SimulationState,
+    world: &World,
+    assets: &AssetServer,
+) {
+    if !event.pressed || simulation_state.is_started() {
+        return;
+    }
+    match event.action {
+        Action::Back => state.pasting = None,
+        Action::SelectAll => {
+            state.selection = world.parts()[state.currently_editing_part]
+                .structure
+                .blocks
+                .iter()
+                .map(|block| block.position)
+                .collect();
+            refresh_selection_markers(commands, state, assets);
+        }
+        Action::Copy => copy_selection(state, world),
+        Action::Paste => begin_paste(state, PasteMode::IntoCurrentPart),
+        Action::PasteAsNewPart => begin_paste(state, PasteMode::AsNewPart),
+        _ => (),
+    }
+}
+
+fn copy_selection(state: &mut InterfaceState, world: &World) {
+    let part = &world.parts()[state.currently_editing_part].structure;
+    let blocks: Vec<_> = part
+        .blocks
+        .iter()
+        .filter(|block| state.selection.contains(&block.position))
+        .copied()
+        .collect();
+    if blocks.is_empty() {
+        return;
+    }
+    let mut clipboard = Structure { blocks };
+    clipboard.normalize();
+    state.clipboard = Some(clipboard);
+}
+
+fn begin_paste(state: &mut InterfaceState, mode: PasteMode) {
+    if state.clipboard.is_some() {
+        state.block_to_place = None;
+        state.pasting = Some(mode);
+        state.paste_facing = BlockFacing::Px;
+    }
+}
+
+pub(super) fn toggle_selection(
+    commands: &mut Commands,
+    state: &mut InterfaceState,
+    world: &World,
+    position: Position,
+    assets: &AssetServer,
+) {
+    if !state.selection.remove(&position) {
+        let part = &world.parts()[state.currently_editing_part].structure;
+        if part.get_block_at(position).is_none() {
+            return;
+        }
+        state.selection.insert(position);
+    }
+    refresh_selection_markers(commands, state, assets);
+}
+
+/// The selection is a set of positions in the part being edited, so it is
+/// dropped when another part is picked, whichever way that happens.
+pub(super) fn clear_selection_on_part_switch(
+    mut commands: Commands,
+    mut state: ResMut<InterfaceState>,
+    assets: Res<AssetServer>,
+    mut selected_in: Local<usize>,
+) {
+    let state = &mut *state;
+    if state.currently_editing_part == *selected_in {
+        return;
+    }
+    *selected_in = state.currently_editing_part;
+    if !state.selection.is_empty() {
+        state.selection.clear();
+        refresh_selection_markers(&mut commands, state, &*assets);
+    }
+}
+
+pub(super) fn refresh_selection_markers(
+    commands: &mut Commands,
+    state: &mut InterfaceState,
+    assets: &AssetServer,
+) {
+    for marker in state.selection_markers.drain(..) {
+        commands.entity(marker).despawn_recursive();
+    }
+    for &position in &state.selection {
+        let marker = commands
+            .spawn()
+            .insert_bundle(SceneBundle {
+                scene: assets.load("blocks/cursor.glb#Scene0"),
+                transform: Transform::from_translation(Vec3::new(
+                    position.0 as f32,
+                    position.1 as f32,
+                    position.2 as f32,
+                )),
+                ..Default::default()
+            })
+            .insert(LevelEntity)
+            .id();
+        state.selection_markers.push(marker);
+    }
+}
+
+/// Keeps the ghost of whatever is about to be pasted under the cursor. Pass
+/// `None` to hide it.
+pub(super) fn update_paste_preview(
+    commands: &mut Commands,
+    state: &mut InterfaceState,
+    position: Option<Position>,
+    assets: &AssetServer,
+) {
+    let wanted = state
+        .pasting
+        .and(state.clipboard.as_ref())
+        .map(|_| state.paste_facing);
+    if let Some((preview, facing)) = state.paste_preview {
+        if Some(facing) != wanted {
+            commands.entity(preview).despawn_recursive();
+            state.paste_preview = None;
+        }
+    }
+    if let (None, Some(clipboard)) = (state.paste_preview, wanted.and(state.clipboard.as_ref())) {
+        let preview =
+            spawn_ghost_structure(&clipboard.rotated(state.paste_facing), commands, assets);
+        state.paste_preview = Some((preview, state.paste_facing));
+    }
+    if let Some((preview, _)) = state.paste_preview {
+        let translation = position
+            .map(|p| Vec3::new(p.0 as f32, p.1 as f32, p.2 as f32))
+            .unwrap_or_default();
+        commands
+            .entity(preview)
+            .insert(Transform::from_translation(translation))
+            .insert(Visibility {
+                is_visible: position.is_some(),
+            });
+    }
+}
+
+fn occupied_by_other_part(world: &World, exclude: usize, position: Position) -> bool {
+    world.parts().iter().enumerate().any(|(index, part)| {
+        index != exclude && !part.is_hologram && part.structure.get_block_at(position).is_some()
+    })
+}
+
+pub(super) fn paste(
+    commands: &mut Commands,
+    state: &mut InterfaceState,
+    world: &mut World,
+    position: Position,
+    assets: &AssetServer,
+    sfx: &Sfx,
+    audio: &Audio,
+    audio_settings: &AudioSettings,
+) {
+    let (mode, clipboard) = match (state.pasting, &state.clipboard) {
+        (Some(mode), Some(clipboard)) => (mode, clipboard),
+        _ => return,
+    };
+    let mut structure = clipboard.rotated(state.paste_facing);
+    structure.translate(position);
+    match mode {
+        PasteMode::IntoCurrentPart => {
+            let target = state.currently_editing_part;
+            structure
+                .blocks
+                .retain(|block| !occupied_by_other_part(world, target, block.position));
+            world.modify_part(
+                target,
+                |part| {
+                    for block in structure.blocks {
+                        part.set_block(block);
+                    }
+                },
+                commands,
+                assets,
+            );
+        }
+        PasteMode::AsNewPart => {
+            structure
+                .blocks
+                .retain(|block| !occupied_by_other_part(world, usize::MAX, block.position));
+            if structure.blocks.is_empty() {
+                return;
+            }
+            world.add_part(structure, commands, assets);
+            state.currently_editing_part = world.parts().len() - 1;
+        }
+    }
+    audio_settings.play(audio, &sfx.place[0], 0.3);
+    if !state.holding_shift {
+        state.pasting = None;
+    }
+}
+
+/// How long messages about solution codes stay up, in seconds.
+const NOTICE_TIME: f32 = 4.0;
+
+fn notify(state: &mut InterfaceState, message: String) {
+    state.notice = Some((message, NOTICE_TIME));
+}
+
+/// Puts a code for the user parts on the system clipboard, so the solution can
+/// be shared.
+pub(super) fn copy_solution_code(state: &mut InterfaceState, world: &World, level: usize) {
+    let parts: Vec<_> = world.parts()[state.first_user_part..]
+        .iter()
+        .map(|part| part.structure.clone())
+        .filter(|structure| !structure.blocks.is_empty())
+        .collect();
+    let code = solution_code::encode(level, &parts);
+    println!("{}", code);
+    let message = match copy_to_clipboard(&code) {
+        Ok(()) => "Copied the solution code".to_owned(),
+        Err(problem) => problem,
+    };
+    notify(state, message);
+}
+
+/// Replaces the user parts with the ones in the code on the clipboard.
+pub(super) fn paste_solution_code(
+    commands: &mut Commands,
+    state: &mut InterfaceState,
+    world: &mut World,
+    level: usize,
+    assets: &AssetServer,
+) {
+    let code = match paste_from_clipboard() {
+        Ok(code) => code,
+        Err(problem) => return notify(state, problem),
+    };
+    let parts = match solution_code::decode(&code) {
+        Ok((for_level, _)) if for_level != level => {
+            return notify(
+                state,
+                format!("That solution is for level {}", for_level + 1),
+            );
+        }
+        Ok((_, parts)) => parts,
+        Err(problem) => {
+            return notify(state, format!("Could not paste the solution, {}", problem));
+        }
+    };
+    let fixed = &world.parts()[..state.first_user_part];
+    let overlaps = parts.iter().flat_map(|part| &part.blocks).any(|block| {
+        fixed
+            .iter()
+            .any(|part| !part.is_hologram && part.structure.get_block_at(block.position).is_some())
+    });
+    if overlaps {
+        return notify(
+            state,
+            "Could not paste the solution, it does not fit this level".to_owned(),
+        );
+    }
+    while world.parts().len() > state.first_user_part {
+        world.remove_part(world.parts().len() - 1, commands);
+    }
+    for part in parts {
+        world.add_part(part, commands, assets);
+    }
+    if world.parts().len() == state.first_user_part {
+        world.add_part(Structure { blocks: Vec::new() }, commands, assets);
+    }
+    state.currently_editing_part = state.first_user_part;
+    state.selection.clear();
+    refresh_selection_markers(commands, state, assets);
+    notify(state, "Pasted the solution".to_owned());
+}
@@ -1,13 +1,9 @@
-use bevy::{
-    input::{keyboard::KeyboardInput, ButtonState},
-    prelude::*,
-};
+use bevy::prelude::*;
 
-use super::{
-    export_level, util::directional_key_index, ChangeToCompleteRequest, InterfaceState, EDITING,
-};
+use super::{export_level, ChangeToCompleteRequest, InterfaceState, EDITING};
 use crate::{
     block::{BlockFacing, BlockKind},
+    controls::{Action, ActionEvent},
     setup_complete::ChangeToMenuRequest,
     setup_menu::GlobalState,
     simulation::SimulationState,
@@ -16,21 +12,26 @@ use crate::{
 
 pub(super) fn update_directional_key(
     commands: &mut Commands,
-    event: &KeyboardInput,
+    event: &ActionEvent,
     state: &mut InterfaceState,
     simulation_state: &SimulationState,
     global_state: &mut GlobalState,
     world: &World,
 ) {
-    if state.block_to_place.is_some() && !simulation_state.is_started() {
+    // Escape closes the split prompt first, whether or not a block is being
+    // placed.
+    if event.action == Action::Back && event.pressed && state.pending_start.take().is_some() {
+        return;
+    }
+    let placing = state.block_to_place.is_some() || state.pasting.is_some();
+    if placing && !simulation_state.is_started() {
         state.movement_keys.fill(false);
         return;
     }
-    let directional_key = directional_key_index(event);
-    if let Some(key) = directional_key {
-        state.movement_keys[key] = event.state == ButtonState::Pressed;
-    } else if event.key_code == Some(KeyCode::Escape) && event.state == ButtonState::Pressed {
-        exit_level(commands, world, state, global_state, false);
+    match event.action {
+        Action::MoveCamera(key) => state.movement_keys[key] = event.pressed,
+        Action::Back if event.pressed => exit_level(commands, world, state, global_state, false),
+        _ => (),
     }
 }
 
@@ -41,8 +42,7 @@ pub fn exit_level(
     global_state: &mut GlobalState,
     complete: bool,
 ) {
-    let level = global_state.current_level;
-    global_state.levels[level] = export_level(world, state.first_user_part);
+    global_state.slot_mut().level = export_level(world, state.first_user_part);
     if complete {
         commands.insert_resource(ChangeToCompleteRequest);
     } else {
@@ -51,39 +51,64 @@ pub fn exit_level(
 }
 
 pub(super) fn update_block_keys(
-    event: &KeyboardInput,
+    event: &ActionEvent,
     state: &mut InterfaceState,
     simulation_state: &SimulationState,
 ) {
-    if event.state != ButtonState::Pressed || simulation_state.is_started() {
+    if !event.pressed || simulation_state.is_started() {
         return;
     }
-    if EDITING {
-        match event.key_code {
-            Some(KeyCode::Escape) => state.block_to_place = None,
-            Some(KeyCode::Key1) => state.block_to_place = Some(BlockKind::DecoStructure),
-            Some(KeyCode::Key2) => state.block_to_place = Some(BlockKind::DecoStructure2),
-            Some(KeyCode::Key3) => state.block_to_place = Some(BlockKind::DecoStructureInput),
-            Some(KeyCode::Key4) => state.block_to_place = Some(BlockKind::DecoStructureOutput),
-            _ => (),
-        }
+    let hotbar: &[BlockKind] = if EDITING {
+        &[
+            BlockKind::DecoStructure,
+            BlockKind::DecoStructure2,
+            BlockKind::DecoStructureInput,
+            BlockKind::DecoStructureOutput,
+        ]
     } else {
-        match event.key_code {
-            Some(KeyCode::Escape) => state.block_to_place = None,
-            Some(KeyCode::Key1) => state.block_to_place = Some(BlockKind::Structure),
-            Some(KeyCode::Key2) => state.block_to_place = Some(BlockKind::TractorBeamSource),
-            Some(KeyCode::Key3) => state.block_to_place = Some(BlockKind::WelderBeamSource),
-            _ => (),
+        &[
+            BlockKind::Structure,
+            BlockKind::TractorBeamSource,
+            BlockKind::WelderBeamSource,
+        ]
+    };
+    match event.action {
+        Action::Back => state.block_to_place = None,
+        Action::SelectBlock(index) => {
+            if let Some(&kind) = hotbar.get(index) {
+                state.block_to_place = Some(kind);
+            }
+        }
+        Action::NextBlockKind | Action::PreviousBlockKind => {
+            let current = state
+                .block_to_place
+                .and_then(|kind| hotbar.iter().position(|&x| x == kind));
+            let index = match (current, event.action) {
+                (None, Action::NextBlockKind) => 0,
+                (None, _) => hotbar.len() - 1,
+                (Some(index), Action::NextBlockKind) => (index + 1) % hotbar.len(),
+                (Some(index), _) => (index + hotbar.len() - 1) % hotbar.len(),
+            };
+            state.block_to_place = Some(hotbar[index]);
         }
+        _ => (),
     }
-    if state.block_to_place.is_some() {
-        match event.key_code {
-            Some(KeyCode::Q) => state.facing = BlockFacing::Ny,
-            Some(KeyCode::W) => state.facing = BlockFacing::Pz,
-            Some(KeyCode::E) => state.facing = BlockFacing::Nx,
-            Some(KeyCode::A) => state.facing = BlockFacing::Px,
-            Some(KeyCode::S) => state.facing = BlockFacing::Nz,
-            Some(KeyCode::D) => state.facing = BlockFacing::Py,
+    if state.block_to_place.is_some() || state.pasting.is_some() {
+        // Pastes keep their own facing so that they come out the way they were
+        // copied until they are turned on purpose.
+        let facing = if state.pasting.is_some() {
+            &mut state.paste_facing
+        } else {
+            &mut state.facing
+        };
+        let facings = BlockFacing::all();
+        let current = facings.iter().position(|x| x == facing).unwrap_or(0);
+        match event.action {
+            Action::RotateFacing(new_facing) => *facing = new_facing,
+            Action::NextFacing => *facing = facings[(current + 1) % facings.len()],
+            Action::PreviousFacing => {
+                *facing = facings[(current + facings.len() - 1) % facings.len()]
+            }
             _ => (),
         }
     }
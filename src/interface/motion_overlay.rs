@@ -0,0 +1,164 @@
+use bevy::{pbr::NotShadowCaster, prelude::*};
+
+use super::InterfaceState;
+use crate::{
+    block::BlockFacing,
+    controls::{Action, ActionEvent},
+    setup::LevelEntity,
+    simulation::{plan_moves, SimulationState},
+    world::{Part, Position, World},
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MotionArrow {
+    position: Vec3,
+    direction: BlockFacing,
+    /// The tractor beam doing the pulling, only set on the part it pulls.
+    beam: Option<Position>,
+    falling: bool,
+    pushed: bool,
+}
+
+fn to_vec3(position: Position) -> Vec3 {
+    Vec3::new(position.0 as f32, position.1 as f32, position.2 as f32)
+}
+
+fn centroid(part: &Part) -> Vec3 {
+    let blocks = &part.structure.blocks;
+    let total: Vec3 = blocks.iter().map(|block| to_vec3(block.position)).sum();
+    total / blocks.len().max(1) as f32
+}
+
+/// One arrow for every part that moves during the next tick. Inputs spawning
+/// and welds happen before parts move, so those are not accounted for.
+fn arrows_for(parts: &[Part]) -> Vec<MotionArrow> {
+    let plan = plan_moves(parts);
+    let mut arrows = Vec::new();
+    for planned in &plan.moves {
+        for &moved in &planned.moved_parts {
+            let pushed = moved != planned.part;
+            arrows.push(MotionArrow {
+                position: centroid(&parts[moved]),
+                direction: planned.direction,
+                beam: if pushed {
+                    None
+                } else {
+                    planned.pulled_by.map(|block| block.position)
+                },
+                falling: planned.pulled_by.is_none(),
+                pushed,
+            });
+        }
+    }
+    arrows
+}
+
+fn arrow_color(arrow: &MotionArrow) -> Color {
+    if arrow.pushed {
+        Color::hex("F4A261").unwrap()
+    } else if arrow.falling {
+        Color::hex("CCCCCC").unwrap()
+    } else {
+        Color::hex("E9C46A").unwrap()
+    }
+}
+
+/// Shows which way every part will move next, while editing or while the
+/// simulation is paused. In edit mode the prediction is made on the parts as
+/// they will be once loose blocks have split off.
+pub fn motion_overlay_system(
+    mut commands: Commands,
+    mut actions: EventReader<ActionEvent>,
+    world: Res<World>,
+    mut state: ResMut<InterfaceState>,
+    simulation_state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in actions.iter() {
+        if event.pressed && event.action == Action::ToggleMotionOverlay {
+            state.show_motion_overlay = !state.show_motion_overlay;
+        }
+    }
+    let arrows = if !state.show_motion_overlay || simulation_state.running {
+        Vec::new()
+    } else if simulation_state.is_started() {
+        arrows_for(world.parts())
+    } else {
+        arrows_for(&world.loose_parts())
+    };
+    if arrows == state.motion_arrows {
+        return;
+    }
+    for marker in state.motion_markers.drain(..) {
+        commands.entity(marker).despawn_recursive();
+    }
+    let shaft = meshes.add(Mesh::from(shape::Box::new(0.6, 0.08, 0.08)));
+    let head = meshes.add(Mesh::from(shape::Cube { size: 0.22 }));
+    let link = meshes.add(Mesh::from(shape::Box::new(1.0, 0.03, 0.03)));
+    for arrow in &arrows {
+        let material = materials.add(StandardMaterial {
+            base_color: arrow_color(arrow),
+            unlit: true,
+            ..Default::default()
+        });
+        let direction = to_vec3(arrow.direction.offset());
+        let marker = commands
+            .spawn()
+            .insert_bundle(SpatialBundle {
+                transform: Transform {
+                    translation: arrow.position,
+                    rotation: Quat::from_rotation_arc(Vec3::X, direction),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(LevelEntity)
+            .with_children(|parent| {
+                parent
+                    .spawn()
+                    .insert_bundle(PbrBundle {
+                        mesh: shaft.clone(),
+                        material: material.clone(),
+                        ..Default::default()
+                    })
+                    .insert(NotShadowCaster);
+                parent
+                    .spawn()
+                    .insert_bundle(PbrBundle {
+                        mesh: head.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_xyz(0.35, 0.0, 0.0)
+                            .with_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_4)),
+                        ..Default::default()
+                    })
+                    .insert(NotShadowCaster);
+            })
+            .id();
+        state.motion_markers.push(marker);
+        // A thin line back to the beam responsible for the pull.
+        if let Some(beam) = arrow.beam {
+            let delta = arrow.position - to_vec3(beam);
+            if delta == Vec3::ZERO {
+                continue;
+            }
+            let marker = commands
+                .spawn()
+                .insert_bundle(PbrBundle {
+                    mesh: link.clone(),
+                    material,
+                    transform: Transform {
+                        translation: to_vec3(beam) + delta / 2.0,
+                        rotation: Quat::from_rotation_arc(Vec3::X, delta.normalize()),
+                        scale: Vec3::new(delta.length(), 1.0, 1.0),
+                    },
+                    ..Default::default()
+                })
+                .insert(NotShadowCaster)
+                .insert(LevelEntity)
+                .id();
+            state.motion_markers.push(marker);
+        }
+    }
+    state.motion_arrows = arrows;
+}
@@ -4,8 +4,13 @@ use bevy::{
 };
 use bevy_mod_raycast::Intersection;
 
-use super::{util::get_mouse_position_in_world, Cursor, InterfaceState, EDITING};
+use super::{
+    clipboard::{paste, refresh_selection_markers, toggle_selection, update_paste_preview},
+    util::get_mouse_position_in_world,
+    Cursor, InterfaceState, EDITING,
+};
 use crate::{
+    audio_settings::AudioSettings,
     block::{Block, BlockFacing, BlockKind, BlockRaycastSet},
     simulation::SimulationState,
     structure::Structure,
@@ -20,26 +25,35 @@ pub(super) fn handle_mouse(
     block_raycast_intersection: Query<(&Intersection<BlockRaycastSet>,)>,
     commands: &mut Commands,
     clicked: bool,
+    remove_clicked: bool,
     world: &mut World,
     assets: &AssetServer,
     sfx: &Sfx,
     audio: &Audio,
+    audio_settings: &AudioSettings,
 ) {
     let [(_, mut place_cursor_visibility), (_, mut remove_cursor_visibility)] = cursor
         .get_many_mut([state.place_cursor, state.remove_cursor])
         .unwrap();
     place_cursor_visibility.is_visible = state.block_to_place.is_some();
     remove_cursor_visibility.is_visible = !state.block_to_place.is_some();
+    if state.pasting.is_some() {
+        place_cursor_visibility.is_visible = false;
+        remove_cursor_visibility.is_visible = false;
+    }
     if simulation_state.is_started() {
         place_cursor_visibility.is_visible = false;
         remove_cursor_visibility.is_visible = false;
+        update_paste_preview(commands, state, None, assets);
         return;
     }
     let mouse_position = get_mouse_position_in_world(&block_raycast_intersection);
+    update_paste_preview(commands, state, mouse_position.map(|x| x.0), assets);
     if let Some((above_cursor, below_cursor)) = mouse_position {
         handle_mouse_events(
             commands,
             clicked,
+            remove_clicked,
             above_cursor,
             below_cursor,
             world,
@@ -47,6 +61,7 @@ pub(super) fn handle_mouse(
             assets,
             sfx,
             audio,
+            audio_settings,
         );
 
         for (mut cursor_transform, _) in cursor.iter_mut() {
@@ -67,6 +82,7 @@ pub(super) fn handle_mouse(
 fn handle_mouse_events(
     commands: &mut Commands,
     clicked: bool,
+    remove_clicked: bool,
     above_cursor: Position,
     below_cursor: Position,
     world: &mut World,
@@ -74,9 +90,35 @@ fn handle_mouse_events(
     assets: &AssetServer,
     sfx: &Sfx,
     audio: &Audio,
+    audio_settings: &AudioSettings,
 ) {
-    if clicked {
-        if let Some(block_to_place) = state.block_to_place {
+    if remove_clicked {
+        remove_block(
+            world,
+            below_cursor,
+            commands,
+            assets,
+            &*state,
+            sfx,
+            audio,
+            audio_settings,
+        );
+        if state.selection.remove(&below_cursor) {
+            refresh_selection_markers(commands, state, assets);
+        }
+    } else if clicked {
+        if state.pasting.is_some() {
+            paste(
+                commands,
+                state,
+                world,
+                above_cursor,
+                assets,
+                sfx,
+                audio,
+                audio_settings,
+            );
+        } else if let Some(block_to_place) = state.block_to_place {
             place_block(
                 block_to_place,
                 state.facing,
@@ -87,12 +129,27 @@ fn handle_mouse_events(
                 assets,
                 sfx,
                 audio,
+                audio_settings,
             );
             if !state.holding_shift {
                 state.block_to_place = None;
             }
+        } else if state.holding_control {
+            toggle_selection(commands, state, world, below_cursor, assets);
         } else {
-            remove_block(world, below_cursor, commands, assets, &*state, sfx, audio);
+            remove_block(
+                world,
+                below_cursor,
+                commands,
+                assets,
+                &*state,
+                sfx,
+                audio,
+                audio_settings,
+            );
+            if state.selection.remove(&below_cursor) {
+                refresh_selection_markers(commands, state, assets);
+            }
         }
     }
 }
@@ -107,6 +164,7 @@ fn place_block(
     assets: &AssetServer,
     sfx: &Sfx,
     audio: &Audio,
+    audio_settings: &AudioSettings,
 ) {
     let index = match kind {
         BlockKind::Structure => 0,
@@ -114,10 +172,7 @@ fn place_block(
         BlockKind::WelderBeamSource => 2,
         _ => 0,
     };
-    audio.play_with_settings(
-        sfx.place[index].clone(),
-        PlaybackSettings::ONCE.with_volume(0.3),
-    );
+    audio_settings.play(audio, &sfx.place[index], 0.3);
     world.modify_part(
         *part_index,
         |part| {
@@ -140,6 +195,7 @@ fn remove_block(
     state: &InterfaceState,
     sfx: &Sfx,
     audio: &Audio,
+    audio_settings: &AudioSettings,
 ) {
     let start = if EDITING { 0 } else { state.first_user_part };
     for part in start..world.parts().len() {
@@ -150,5 +206,5 @@ fn remove_block(
             assets,
         );
     }
-    audio.play_with_settings(sfx.click.clone(), PlaybackSettings::ONCE.with_volume(0.3));
+    audio_settings.play(audio, &sfx.click, 0.3);
 }
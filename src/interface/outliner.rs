@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+
+use super::InterfaceState;
+use crate::{
+    simulation::SimulationState,
+    tint::Tint,
+    world::{Part, World},
+};
+
+// Layout of the panel, in the same 720-pixel-tall coordinates as the rest of
+// the click handling.
+const BOTTOM: f32 = 180.0;
+const WIDTH: f32 = 300.0;
+const HEIGHT: f32 = 432.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MAX_ROWS: usize = (HEIGHT / ROW_HEIGHT) as usize;
+const HIDE_BUTTON_X: f32 = 230.0;
+const SOLO_BUTTON_X: f32 = 265.0;
+
+pub fn part_color(part_index: usize) -> Color {
+    let palette = [
+        "2A9D8F", "E9C46A", "F4A261", "E76F51", "8AB17D", "A2D2FF", "CDB4DB",
+    ];
+    Color::hex(palette[part_index % palette.len()]).unwrap()
+}
+
+fn part_tint(part_index: usize) -> Color {
+    let c = part_color(part_index);
+    Color::rgb(0.5 + 0.5 * c.r(), 0.5 + 0.5 * c.g(), 0.5 + 0.5 * c.b())
+}
+
+pub(super) fn is_part_visible(state: &InterfaceState, part: &Part) -> bool {
+    if let Some(solo) = state.solo_part {
+        solo == part.physical_instance
+    } else {
+        !state.hidden_parts.contains(&part.physical_instance)
+    }
+}
+
+/// Stopping the simulation puts the parts from before it back as new entities
+/// in the same order, so the hidden and solo parts are moved over to those.
+pub(super) fn follow_restored_parts(state: &mut InterfaceState, snapshot: &World, world: &World) {
+    let restored = |entity: Entity| {
+        let index = snapshot
+            .parts()
+            .iter()
+            .position(|part| part.physical_instance == entity)?;
+        world.parts().get(index).map(|part| part.physical_instance)
+    };
+    state.hidden_parts = state
+        .hidden_parts
+        .iter()
+        .filter_map(|&entity| restored(entity))
+        .collect();
+    state.solo_part = state.solo_part.and_then(restored);
+}
+
+/// Tints and hides the user's parts according to the outliner, making the part
+/// being edited glow and dimming the rest. Only done while editing, since the
+/// simulation respawns every part when it starts.
+pub fn part_appearance_system(
+    mut commands: Commands,
+    world: Res<World>,
+    state: Res<InterfaceState>,
+    simulation_state: Res<SimulationState>,
+) {
+    if simulation_state.is_started() {
+        return;
+    }
+    for (index, part) in world.parts().iter().enumerate().skip(state.first_user_part) {
+        let tint = if index == state.currently_editing_part {
+            Tint {
+                emissive: part_color(index) * 0.3,
+                ..Tint::new(part_tint(index))
+            }
+        } else {
+            Tint::new(part_tint(index) * 0.55)
+        };
+        commands
+            .entity(part.physical_instance)
+            .insert(tint)
+            .insert(Visibility {
+                is_visible: is_part_visible(&*state, part),
+            });
+    }
+}
+
+/// Returns true if the click landed on the panel.
+pub(super) fn handle_outliner_click(
+    state: &mut InterfaceState,
+    parts: &[Part],
+    cursor_pos: Vec2,
+) -> bool {
+    let local = cursor_pos - Vec2::new(0.0, BOTTOM);
+    if local.x < 0.0 || local.x > WIDTH || local.y < 0.0 || local.y > HEIGHT {
+        return false;
+    }
+    // Row zero is the heading.
+    let row = ((HEIGHT - local.y) / ROW_HEIGHT) as usize;
+    if row == 0 {
+        return true;
+    }
+    let part_index = state.first_user_part + row - 1;
+    let entity = match parts.get(part_index) {
+        Some(part) => part.physical_instance,
+        None => return true,
+    };
+    if local.x >= SOLO_BUTTON_X {
+        if state.solo_part == Some(entity) {
+            state.solo_part = None;
+        } else {
+            state.solo_part = Some(entity);
+        }
+    } else if local.x >= HIDE_BUTTON_X {
+        if !state.hidden_parts.remove(&entity) {
+            state.hidden_parts.insert(entity);
+        }
+    } else {
+        state.currently_editing_part = part_index;
+    }
+    true
+}
+
+fn spawn_text(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    value: String,
+    color: Color,
+    left: f32,
+) -> Entity {
+    commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value,
+                    style: TextStyle {
+                        font: assets.load("RobotoSlab-Regular.ttf"),
+                        font_size: 18.0,
+                        color,
+                    },
+                }],
+                alignment: TextAlignment::CENTER_LEFT,
+            },
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(left / WIDTH * 100.0),
+                    top: Val::Percent(10.0),
+                    ..Default::default()
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id()
+}
+
+fn make_row(commands: &mut Commands, row: usize, highlighted: bool) -> Entity {
+    let row_percent = ROW_HEIGHT / HEIGHT * 100.0;
+    commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(0.0),
+                    top: Val::Percent(row as f32 * row_percent),
+                    ..Default::default()
+                },
+                size: Size {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(row_percent),
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(if highlighted {
+                Color::rgba(1.0, 1.0, 1.0, 0.15)
+            } else {
+                Color::NONE
+            }),
+            ..Default::default()
+        })
+        .id()
+}
+
+fn make_swatch(commands: &mut Commands, color: Color) -> Entity {
+    commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(8.0 / WIDTH * 100.0),
+                    top: Val::Percent(25.0),
+                    ..Default::default()
+                },
+                size: Size {
+                    width: Val::Auto,
+                    height: Val::Percent(50.0),
+                },
+                aspect_ratio: Some(1.0),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(color),
+            ..Default::default()
+        })
+        .id()
+}
+
+pub(super) fn make_outliner(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    state: &InterfaceState,
+    parts: &[Part],
+) -> Entity {
+    let root = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(0.0),
+                    bottom: Val::Percent(BOTTOM / 720.0 * 100.0),
+                    ..Default::default()
+                },
+                size: Size {
+                    width: Val::Auto,
+                    height: Val::Percent(HEIGHT / 720.0 * 100.0),
+                },
+                aspect_ratio: Some(WIDTH / HEIGHT),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+            ..Default::default()
+        })
+        .id();
+    let heading = make_row(commands, 0, false);
+    commands.entity(root).add_child(heading);
+    let text = spawn_text(commands, assets, "Parts".to_owned(), Color::WHITE, 8.0);
+    commands.entity(heading).add_child(text);
+
+    let user_parts = parts.iter().enumerate().skip(state.first_user_part);
+    for (row, (index, part)) in user_parts.take(MAX_ROWS - 1).enumerate() {
+        let row = make_row(commands, row + 1, index == state.currently_editing_part);
+        commands.entity(root).add_child(row);
+
+        let pieces = part.structure.loose_pieces().len();
+        let mut label = format!(
+            "{}: {} blocks",
+            index + 1 - state.first_user_part,
+            part.structure.blocks.len()
+        );
+        if pieces > 1 {
+            label.push_str(&format!(", {} pieces", pieces));
+        }
+        let color = if pieces > 1 {
+            Color::hex("E76F51").unwrap()
+        } else {
+            Color::WHITE
+        };
+        let swatch = make_swatch(commands, part_color(index));
+        let text = spawn_text(commands, assets, label, color, 26.0);
+        let hide_color = if state.hidden_parts.contains(&part.physical_instance) {
+            Color::rgba(1.0, 1.0, 1.0, 0.4)
+        } else {
+            Color::WHITE
+        };
+        let hide = spawn_text(
+            commands,
+            assets,
+            "H".to_owned(),
+            hide_color,
+            HIDE_BUTTON_X + 8.0,
+        );
+        let solo_color = if state.solo_part == Some(part.physical_instance) {
+            Color::hex("E9C46A").unwrap()
+        } else {
+            Color::rgba(1.0, 1.0, 1.0, 0.4)
+        };
+        let solo = spawn_text(
+            commands,
+            assets,
+            "S".to_owned(),
+            solo_color,
+            SOLO_BUTTON_X + 8.0,
+        );
+        commands
+            .entity(row)
+            .push_children(&[swatch, text, hide, solo]);
+    }
+    root
+}
@@ -1,7 +1,9 @@
 use bevy::{input::keyboard::KeyboardInput, prelude::*};
 
 use super::{make_ui, Cursor, InterfaceMode, InterfaceState, EDITING};
-use crate::{block::BlockFacing, setup::LevelEntity, simulation::SimulationState, setup_menu::GlobalState};
+use crate::{
+    block::BlockFacing, setup::LevelEntity, setup_menu::GlobalState, simulation::SimulationState,
+};
 
 pub fn setup_interface_state(
     commands: &mut Commands,
@@ -39,11 +41,35 @@ pub fn setup_interface_state(
         block_to_place: None,
         facing: BlockFacing::Nx,
         holding_shift: false,
+        holding_control: false,
+        selection: Default::default(),
+        selection_markers: Vec::new(),
+        clipboard: global_state.clipboard.clone(),
+        pasting: None,
+        paste_facing: BlockFacing::Px,
+        paste_preview: None,
+        hidden_parts: Default::default(),
+        solo_part: None,
+        pending_start: None,
+        split_warning: Vec::new(),
+        split_pieces: Vec::new(),
+        split_markers: Vec::new(),
+        notice: None,
+        show_motion_overlay: false,
+        motion_arrows: Vec::new(),
+        motion_markers: Vec::new(),
         place_cursor,
         remove_cursor,
         ui_root,
     };
-    let ui_root = make_ui(commands, assets, &state, simulation_state, global_state);
+    let ui_root = make_ui(
+        commands,
+        assets,
+        &state,
+        simulation_state,
+        global_state,
+        &[],
+    );
     state.ui_root = ui_root;
     commands.insert_resource(state);
 }
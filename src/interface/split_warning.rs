@@ -0,0 +1,172 @@
+use bevy::{pbr::NotShadowCaster, prelude::*};
+
+use super::{outliner::part_color, InterfaceState};
+use crate::{
+    setup::LevelEntity,
+    setup_menu::GlobalState,
+    simulation::{self, SimulationState},
+    world::{World, WorldSnapshot},
+};
+
+/// Starts the simulation, unless some parts are about to fall apart and the
+/// player has not been warned about it yet.
+pub(super) fn request_begin_simulation(
+    world: &mut World,
+    world_snapshot: &mut WorldSnapshot,
+    simulation_state: &mut SimulationState,
+    state: &mut InterfaceState,
+    global_state: &GlobalState,
+    speed: f32,
+    commands: &mut Commands,
+    assets: &AssetServer,
+) {
+    if !simulation_state.is_started()
+        && global_state.confirm_splits
+        && state.pending_start.is_none()
+    {
+        let report = world.loose_part_report();
+        if !report.is_empty() {
+            state.pending_start = Some(speed);
+            state.split_warning = report;
+            return;
+        }
+    }
+    state.pending_start = None;
+    simulation::begin_simulation(
+        world,
+        world_snapshot,
+        simulation_state,
+        speed,
+        commands,
+        assets,
+    );
+}
+
+/// Shows which blocks of the part being edited will end up as separate parts.
+pub fn split_highlight_system(
+    mut commands: Commands,
+    world: Res<World>,
+    mut state: ResMut<InterfaceState>,
+    simulation_state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let cep = state.currently_editing_part;
+    let pieces = if simulation_state.is_started() || cep >= world.parts().len() {
+        Vec::new()
+    } else {
+        let pieces = world.parts()[cep].structure.loose_pieces();
+        if pieces.len() > 1 {
+            pieces
+        } else {
+            Vec::new()
+        }
+    };
+    if pieces == state.split_pieces {
+        return;
+    }
+    for marker in state.split_markers.drain(..) {
+        commands.entity(marker).despawn_recursive();
+    }
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 1.05 }));
+    for (index, piece) in pieces.iter().enumerate() {
+        let mut color = part_color(index);
+        color.set_a(0.4);
+        let material = materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..Default::default()
+        });
+        for block in &piece.blocks {
+            let p = block.position;
+            let marker = commands
+                .spawn()
+                .insert_bundle(PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(Vec3::new(
+                        p.0 as f32, p.1 as f32, p.2 as f32,
+                    )),
+                    ..Default::default()
+                })
+                .insert(NotShadowCaster)
+                .insert(LevelEntity)
+                .id();
+            state.split_markers.push(marker);
+        }
+    }
+    state.split_pieces = pieces;
+}
+
+pub(super) fn make_split_warning(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    state: &InterfaceState,
+) -> Entity {
+    let root = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position: UiRect {
+                    bottom: Val::Percent(40.0),
+                    left: Val::Percent(30.0),
+                    ..Default::default()
+                },
+                size: Size {
+                    width: Val::Percent(40.0),
+                    height: Val::Percent(25.0),
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(if state.pending_start.is_some() {
+                Color::rgba(0.0, 0.0, 0.0, 0.7)
+            } else {
+                Color::NONE
+            }),
+            ..Default::default()
+        })
+        .id();
+    if state.pending_start.is_none() {
+        return root;
+    }
+    let mut message =
+        "Some parts are not connected and will\nsplit apart when the machine starts:\n\n"
+            .to_owned();
+    for &(part_index, pieces) in &state.split_warning {
+        message.push_str(&format!(
+            "Part {} will become {} parts.\n",
+            part_index + 1 - state.first_user_part,
+            pieces
+        ));
+    }
+    message.push_str("\nPress play or Enter to run anyway,\nEscape to cancel, N to stop asking.");
+    let text = commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: message,
+                    style: TextStyle {
+                        font: assets.load("RobotoSlab-Regular.ttf"),
+                        font_size: 24.0,
+                        ..Default::default()
+                    },
+                }],
+                alignment: TextAlignment::TOP_LEFT,
+            },
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(5.0),
+                    top: Val::Percent(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+    commands.entity(root).add_child(text);
+    root
+}
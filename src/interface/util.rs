@@ -1,4 +1,4 @@
-use bevy::{input::keyboard::KeyboardInput, prelude::*};
+use bevy::prelude::*;
 use bevy_mod_raycast::Intersection;
 
 use crate::{block::BlockRaycastSet, world::Position};
@@ -26,14 +26,3 @@ pub(super) fn get_mouse_position_in_world(
         None
     }
 }
-
-pub(super) fn directional_key_index(event: &KeyboardInput) -> Option<usize> {
-    let directional_key = match event.key_code {
-        Some(KeyCode::W) => Some(0),
-        Some(KeyCode::A) => Some(1),
-        Some(KeyCode::S) => Some(2),
-        Some(KeyCode::D) => Some(3),
-        _ => None,
-    };
-    directional_key
-}
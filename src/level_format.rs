@@ -0,0 +1,337 @@
+//! Reading and writing level files. The current version lists runs of blocks
+//! along x, one run per line under the name of the section it is in:
+//!
+//! ```text
+//! version 2
+//! floor
+//! #4 -20 -20 -1 41
+//! input
+//! i4 0 0 0
+//! ```
+//!
+//! Each run is a block code, the position of its first block and how many
+//! blocks long it is if more than one. Files without a version line are the
+//! older dense grids, which can still be read.
+
+use crate::{
+    block::{Block, BlockFacing, BlockKind},
+    structure::Structure,
+};
+
+pub const VERSION: u32 = 2;
+
+/// Far longer than anything in a level, it only keeps a broken file from
+/// asking for all the memory there is.
+const MAX_RUN_LENGTH: i32 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Section {
+    Floor,
+    Input,
+    Output,
+    /// Parts the player placed.
+    Part,
+}
+
+impl Section {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Section::Floor => "floor",
+            Section::Input => "input",
+            Section::Output => "output",
+            Section::Part => "part",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "floor" => Some(Section::Floor),
+            "input" => Some(Section::Input),
+            "output" => Some(Section::Output),
+            "part" => Some(Section::Part),
+            _ => None,
+        }
+    }
+}
+
+pub type Level = Vec<(Section, Structure)>;
+
+pub fn block_code(kind: BlockKind, facing: BlockFacing) -> String {
+    let c = match kind {
+        BlockKind::DecoStructure => '#',
+        BlockKind::DecoStructure2 => 'x',
+        BlockKind::DecoStructureInput => 'i',
+        BlockKind::DecoStructureOutput => 'o',
+        BlockKind::Structure => 's',
+        BlockKind::Activator => 'a',
+        BlockKind::TractorBeamSource => 't',
+        BlockKind::WelderBeamSource => 'w',
+        BlockKind::LaserSource => 'l',
+        BlockKind::LaserSensor => 'n',
+    };
+    let f = match facing {
+        BlockFacing::Px => '0',
+        BlockFacing::Py => '1',
+        BlockFacing::Nx => '2',
+        BlockFacing::Ny => '3',
+        BlockFacing::Pz => '4',
+        BlockFacing::Nz => '5',
+    };
+    format!("{}{}", c, f)
+}
+
+pub fn parse_block_code(c: char, f: char) -> Result<(BlockKind, BlockFacing), String> {
+    let kind = match c {
+        '#' => BlockKind::DecoStructure,
+        'x' => BlockKind::DecoStructure2,
+        'i' => BlockKind::DecoStructureInput,
+        'o' => BlockKind::DecoStructureOutput,
+        's' => BlockKind::Structure,
+        'a' => BlockKind::Activator,
+        't' => BlockKind::TractorBeamSource,
+        'w' => BlockKind::WelderBeamSource,
+        'l' => BlockKind::LaserSource,
+        'n' => BlockKind::LaserSensor,
+        other => return Err(format!("unknown block \"{}\"", other)),
+    };
+    let facing = match f {
+        '0' => BlockFacing::Px,
+        '1' => BlockFacing::Py,
+        '2' => BlockFacing::Nx,
+        '3' => BlockFacing::Ny,
+        '4' => BlockFacing::Pz,
+        '5' => BlockFacing::Nz,
+        other => return Err(format!("unknown facing \"{}\"", other)),
+    };
+    Ok((kind, facing))
+}
+
+/// Reads either version of the format.
+pub fn parse_level(text: &str) -> Result<Level, String> {
+    let mut lines = text.lines();
+    let first = lines.next().ok_or("the level is empty")?;
+    match first.strip_prefix("version ") {
+        Some(version) if version.trim() == VERSION.to_string() => parse_runs(lines),
+        Some(version) => Err(format!("unknown level version {}", version)),
+        None => parse_dense(text),
+    }
+}
+
+fn parse_runs<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Level, String> {
+    let mut level: Level = Vec::new();
+    for (number, line) in lines.enumerate() {
+        // The version line is not part of `lines`.
+        let at = |problem: String| format!("line {}: {}", number + 2, problem);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(section) = Section::parse(line) {
+            level.push((section, Structure { blocks: Vec::new() }));
+            continue;
+        }
+        let (_, structure) = level
+            .last_mut()
+            .ok_or_else(|| at("blocks before the first section".to_owned()))?;
+        let mut words = line.split_whitespace();
+        let code: Vec<char> = words.next().unwrap().chars().collect();
+        let (kind, facing) = match code[..] {
+            [c, f] => parse_block_code(c, f).map_err(at)?,
+            _ => return Err(at(format!("unknown block \"{}\"", line))),
+        };
+        let numbers: Result<Vec<i32>, _> = words.map(str::parse).collect();
+        let (x, y, z, length) = match numbers.as_deref() {
+            Ok(&[x, y, z]) => (x, y, z, 1),
+            Ok(&[x, y, z, length]) if length > 0 => (x, y, z, length),
+            _ => return Err(at(format!("expected a position in \"{}\"", line))),
+        };
+        if length > MAX_RUN_LENGTH || x.checked_add(length - 1).is_none() {
+            return Err(at(format!("the run in \"{}\" is too long", line)));
+        }
+        for offset in 0..length {
+            structure.blocks.push(Block {
+                kind,
+                facing,
+                position: (x + offset, y, z),
+            });
+        }
+    }
+    match level.first() {
+        Some((Section::Floor, _)) => Ok(level),
+        _ => Err("the level does not start with the floor".to_owned()),
+    }
+}
+
+/// The first format, a grid over the bounding box of the whole level for
+/// every part, two characters per cell.
+fn parse_dense(text: &str) -> Result<Level, String> {
+    let mut lines = text.lines();
+    let start: Result<Vec<i32>, _> = lines.next().unwrap().split(' ').map(str::parse).collect();
+    let (min_x, min_y, min_z) = match start.as_deref() {
+        Ok(&[x, y, z]) => (x, y, z),
+        _ => return Err("the level does not start with its position".to_owned()),
+    };
+    if lines.next() != Some("floor") {
+        return Err("the level does not start with the floor".to_owned());
+    }
+    let mut level: Level = vec![(Section::Floor, Structure { blocks: Vec::new() })];
+    let mut position = (min_x, min_y, min_z);
+    for line in lines {
+        if let Some(section) = Section::parse(line) {
+            level.push((section, Structure { blocks: Vec::new() }));
+            position = (min_x, min_y, min_z);
+        } else if line.is_empty() {
+            position.1 = min_y;
+            position.2 += 1;
+        } else {
+            let cells: Vec<char> = line.chars().collect();
+            for (index, cell) in cells.chunks(2).enumerate() {
+                let (c, f) = match *cell {
+                    ['.', _] => continue,
+                    [c, f] => (c, f),
+                    _ => return Err(format!("odd length row \"{}\"", line)),
+                };
+                let (kind, facing) = parse_block_code(c, f)?;
+                let (_, structure) = level.last_mut().unwrap();
+                structure.blocks.push(Block {
+                    kind,
+                    facing,
+                    position: (min_x + index as i32, position.1, position.2),
+                });
+            }
+            position.1 += 1;
+        }
+    }
+    Ok(level)
+}
+
+pub fn write_level(level: &[(Section, Structure)]) -> String {
+    let mut output = format!("version {}\n", VERSION);
+    for (section, structure) in level {
+        output.push_str(section.name());
+        output.push('\n');
+        let mut blocks = structure.blocks.clone();
+        blocks.sort_by_key(|block| {
+            let (x, y, z) = block.position;
+            (z, y, x)
+        });
+        let mut blocks = blocks.into_iter().peekable();
+        while let Some(first) = blocks.next() {
+            let mut length = 1;
+            while let Some(next) = blocks.peek() {
+                let (x, y, z) = first.position;
+                let continues = next.kind == first.kind
+                    && next.facing == first.facing
+                    && next.position == (x + length, y, z);
+                if !continues {
+                    break;
+                }
+                length += 1;
+                blocks.next();
+            }
+            let (x, y, z) = first.position;
+            output.push_str(&block_code(first.kind, first.facing));
+            output.push_str(&format!(" {} {} {}", x, y, z));
+            if length > 1 {
+                output.push_str(&format!(" {}", length));
+            }
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Rewrites level files in the current format, for
+/// `cargo run -- --convert-levels assets/levels/*.level.txt`.
+pub fn convert_files(paths: &[String]) {
+    for path in paths {
+        let converted = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| parse_level(&text))
+            .map(|level| write_level(&level));
+        let result = converted
+            .and_then(|text| std::fs::write(path, text).map_err(|error| error.to_string()));
+        match result {
+            Ok(()) => println!("Converted {}", path),
+            Err(error) => println!("Could not convert {}: {}", path, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(kind: BlockKind, position: (i32, i32, i32)) -> Block {
+        Block {
+            kind,
+            facing: BlockFacing::Pz,
+            position,
+        }
+    }
+
+    #[test]
+    fn levels_round_trip() {
+        let floor: Vec<_> = (-3..=3)
+            .map(|x| block(BlockKind::DecoStructure, (x, 0, -1)))
+            .chain([block(BlockKind::DecoStructureInput, (4, 0, -1))])
+            .chain([block(BlockKind::DecoStructure, (6, 0, -1))])
+            .collect();
+        let part = vec![
+            block(BlockKind::Structure, (0, 0, 0)),
+            Block {
+                facing: BlockFacing::Nx,
+                ..block(BlockKind::TractorBeamSource, (1, 0, 0))
+            },
+            block(BlockKind::Structure, (0, 0, 1)),
+        ];
+        let level: Level = vec![
+            (Section::Floor, Structure { blocks: floor }),
+            (
+                Section::Input,
+                Structure {
+                    blocks: vec![block(BlockKind::DecoStructure2, (4, 0, 0))],
+                },
+            ),
+            (
+                Section::Output,
+                Structure {
+                    blocks: vec![block(BlockKind::DecoStructure2, (6, 0, 0))],
+                },
+            ),
+            (Section::Part, Structure { blocks: part }),
+        ];
+        assert_eq!(parse_level(&write_level(&level)), Ok(level));
+    }
+
+    #[test]
+    fn shipped_levels_are_in_the_current_format() {
+        for level in 0..10 {
+            let path = format!(
+                "{}/assets/levels/{}.level.txt",
+                env!("CARGO_MANIFEST_DIR"),
+                level
+            );
+            let text = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(write_level(&parse_level(&text).unwrap()), text, "{}", path);
+        }
+    }
+
+    #[test]
+    fn dense_levels_convert_block_for_block() {
+        let dense = include_str!("../tests/data/0.dense.level.txt");
+        let converted = include_str!("../assets/levels/0.level.txt");
+        let dense = parse_level(dense).unwrap();
+        assert_eq!(parse_level(converted), Ok(dense.clone()));
+        assert_eq!(write_level(&dense), converted);
+    }
+
+    #[test]
+    fn runs_are_limited() {
+        assert!(parse_level("version 2\nfloor\n#4 0 0 -1 1000\n").is_ok());
+        assert!(parse_level("version 2\nfloor\n#4 0 0 -1 1001\n").is_err());
+        assert!(parse_level("version 2\nfloor\n#4 2147483647 0 -1 2\n").is_err());
+        assert!(parse_level("version 2\nfloor\n#4 0 0 -1 0\n").is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Checks that a level file makes sense before anyone has to play it: that
+//! its parts are in the order `import_level` expects, that inputs can spawn
+//! and leave, and that the output can be built from the inputs.
+
+use std::fmt;
+
+use bevy::utils::HashSet;
+
+use crate::{
+    block::BlockFacing,
+    generator::made_of,
+    level_format::{parse_level, Level, Section},
+    structure::Structure,
+    world::Position,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    /// The level can be played but is probably not what was meant.
+    Warning,
+    /// The level loads wrong or can not be finished.
+    Error,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Problems(Vec<Problem>);
+
+impl Problems {
+    fn warning(&mut self, message: String) {
+        self.0.push(Problem {
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    fn error(&mut self, message: String) {
+        self.0.push(Problem {
+            severity: Severity::Error,
+            message,
+        });
+    }
+}
+
+fn add(a: Position, b: Position) -> Position {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn positions(structure: &Structure) -> HashSet<Position> {
+    structure
+        .blocks
+        .iter()
+        .map(|block| block.position)
+        .collect()
+}
+
+/// The first position of `structure` that is also in `occupied`, and how many
+/// there are.
+fn overlap(structure: &Structure, occupied: &HashSet<Position>) -> Option<(Position, usize)> {
+    let overlapping: Vec<_> = structure
+        .blocks
+        .iter()
+        .map(|block| block.position)
+        .filter(|position| occupied.contains(position))
+        .collect();
+    overlapping.first().map(|&first| (first, overlapping.len()))
+}
+
+fn rests_on(structure: &Structure, floor: &HashSet<Position>) -> bool {
+    structure
+        .blocks
+        .iter()
+        .any(|block| floor.contains(&add(block.position, BlockFacing::Nz.offset())))
+}
+
+/// Whether the floor keeps the structure from moving one step in every
+/// direction a tractor beam could pull it.
+fn boxed_in(structure: &Structure, floor: &HashSet<Position>) -> bool {
+    [
+        BlockFacing::Px,
+        BlockFacing::Py,
+        BlockFacing::Nx,
+        BlockFacing::Ny,
+        BlockFacing::Pz,
+    ]
+    .into_iter()
+    .all(|direction| {
+        structure
+            .blocks
+            .iter()
+            .any(|block| floor.contains(&add(block.position, direction.offset())))
+    })
+}
+
+/// Whether `total` is a sum of the sizes, using at least one of them.
+fn sum_of(sizes: &[usize], total: usize) -> bool {
+    let mut possible = vec![false; total + 1];
+    possible[0] = true;
+    for n in 1..=total {
+        possible[n] = sizes
+            .iter()
+            .any(|&size| size > 0 && size <= n && possible[n - size]);
+    }
+    total > 0 && possible[total]
+}
+
+fn check_order(level: &Level, problems: &mut Problems) {
+    match level.first() {
+        Some((Section::Floor, _)) => (),
+        Some(_) => problems.error("the level does not start with the floor".to_owned()),
+        None => problems.error("the level is empty".to_owned()),
+    }
+    let floors = level
+        .iter()
+        .filter(|(section, _)| *section == Section::Floor)
+        .count();
+    if floors > 1 {
+        problems.error(format!(
+            "there are {} floors, everything after the first is loaded as something else",
+            floors
+        ));
+    }
+    let outputs = level
+        .iter()
+        .filter(|(section, _)| *section == Section::Output)
+        .count();
+    match outputs {
+        0 => problems.error("there is no output".to_owned()),
+        1 => (),
+        _ => problems.error(format!(
+            "there are {} outputs, all but the last are saved back as inputs",
+            outputs
+        )),
+    }
+    if !level.iter().any(|(section, _)| *section == Section::Input) {
+        problems.error("there are no inputs".to_owned());
+    }
+    let output_index = level
+        .iter()
+        .position(|(section, _)| *section == Section::Output);
+    let mut seen_part = false;
+    for (index, (section, _)) in level.iter().enumerate() {
+        match section {
+            Section::Input if output_index.map_or(false, |output| index > output) => {
+                problems.error(format!(
+                    "part {} is an input after the output, the output has to come last",
+                    index
+                ));
+            }
+            Section::Input | Section::Output if seen_part => {
+                problems.error(format!(
+                    "part {} is an {} after a player part, which shifts where player parts start",
+                    index,
+                    section.name()
+                ));
+            }
+            Section::Part => seen_part = true,
+            _ => (),
+        }
+    }
+}
+
+fn check_floor_overlap(
+    inputs: &[&Structure],
+    output: Option<&Structure>,
+    floor: &HashSet<Position>,
+    problems: &mut Problems,
+) {
+    let named = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| (format!("input {}", index + 1), *input))
+        .chain(output.map(|output| ("the output".to_owned(), output)));
+    for (name, structure) in named {
+        if let Some((position, count)) = overlap(structure, floor) {
+            problems.error(format!(
+                "{} overlaps the floor in {} blocks, the first at {:?}",
+                name, count, position
+            ));
+        }
+    }
+}
+
+fn check_spawns(
+    inputs: &[&Structure],
+    output: Option<&Structure>,
+    floor: &HashSet<Position>,
+    problems: &mut Problems,
+) {
+    for (index, input) in inputs.iter().enumerate() {
+        let number = index + 1;
+        if input.blocks.is_empty() {
+            problems.error(format!("input {} has no blocks", number));
+            continue;
+        }
+        if boxed_in(input, floor) {
+            problems.error(format!(
+                "input {} is boxed in by the floor, so it can never be moved off its spawn",
+                number
+            ));
+        } else if !rests_on(input, floor) {
+            problems.warning(format!(
+                "input {} does not rest on the floor and falls as soon as it spawns",
+                number
+            ));
+        }
+        for (other_index, other) in inputs.iter().enumerate().skip(index + 1) {
+            if let Some((position, _)) = overlap(other, &positions(input)) {
+                problems.warning(format!(
+                    "inputs {} and {} share {:?}, only one of them can be there at a time",
+                    number,
+                    other_index + 1,
+                    position
+                ));
+            }
+        }
+        if let Some((position, _)) = output.and_then(|output| overlap(output, &positions(input))) {
+            problems.warning(format!(
+                "input {} spawns inside the output at {:?}",
+                number, position
+            ));
+        }
+    }
+}
+
+fn check_output(
+    inputs: &[&Structure],
+    output: &Structure,
+    floor: &HashSet<Position>,
+    problems: &mut Problems,
+) {
+    if output.blocks.is_empty() {
+        problems.error("the output has no blocks".to_owned());
+        return;
+    }
+    if !rests_on(output, floor) {
+        problems.warning("the output does not rest on the floor".to_owned());
+    }
+    let sizes: Vec<usize> = inputs.iter().map(|input| input.blocks.len()).collect();
+    if !sum_of(&sizes, output.blocks.len()) {
+        problems.error(format!(
+            "the output has {} blocks, which no number of inputs of {:?} blocks adds up to",
+            output.blocks.len(),
+            sizes
+        ));
+        return;
+    }
+    let inputs: Vec<Structure> = inputs.iter().map(|&input| input.clone()).collect();
+    if !made_of(&inputs, output) {
+        problems
+            .warning("the output can not be split up into moved copies of the inputs".to_owned());
+    }
+}
+
+pub fn validate(level: &Level) -> Vec<Problem> {
+    let mut problems = Problems::default();
+    check_order(level, &mut problems);
+    let floor = match level.first() {
+        Some((Section::Floor, floor)) => positions(floor),
+        _ => HashSet::new(),
+    };
+    let inputs: Vec<&Structure> = level
+        .iter()
+        .filter(|(section, _)| *section == Section::Input)
+        .map(|(_, structure)| structure)
+        .collect();
+    let output = level
+        .iter()
+        .rev()
+        .find(|(section, _)| *section == Section::Output)
+        .map(|(_, structure)| structure);
+    check_floor_overlap(&inputs, output, &floor, &mut problems);
+    check_spawns(&inputs, output, &floor, &mut problems);
+    if let Some(output) = output {
+        if !inputs.is_empty() {
+            check_output(&inputs, output, &floor, &mut problems);
+        }
+    }
+    problems.0
+}
+
+/// Same as `validate`, with a file that does not parse as one error.
+pub fn validate_text(text: &str) -> Vec<Problem> {
+    match parse_level(text) {
+        Ok(level) => validate(&level),
+        Err(error) => vec![Problem {
+            severity: Severity::Error,
+            message: error,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::generate, level_format::write_level};
+
+    fn errors(problems: Vec<Problem>) -> Vec<Problem> {
+        problems
+            .into_iter()
+            .filter(|problem| problem.severity == Severity::Error)
+            .collect()
+    }
+
+    #[test]
+    fn shipped_levels_are_valid() {
+        let directory = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.to_string_lossy().ends_with(".level.txt") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            let errors = errors(validate_text(&text));
+            assert!(errors.is_empty(), "{}: {:?}", path.display(), errors);
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn generated_levels_are_valid() {
+        for seed in 0..20 {
+            let text = write_level(&generate(seed));
+            assert_eq!(validate_text(&text), vec![], "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn misplaced_output_is_an_error() {
+        let text = "version 2\nfloor\n#4 0 0 -1 3\noutput\no4 0 0 -1\ninput\nx4 1 0 0\n";
+        let messages: Vec<_> = errors(validate_text(text))
+            .into_iter()
+            .map(|problem| problem.message)
+            .collect();
+        assert!(messages.iter().any(|x| x.contains("after the output")));
+        assert!(messages.iter().any(|x| x.contains("overlaps the floor")));
+    }
+}
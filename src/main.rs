@@ -1,27 +1,57 @@
 // Music by <a href="https://pixabay.com/users/sergepavkinmusic-6130722/?utm_source=link-attribution&amp;utm_medium=referral&amp;utm_campaign=music&amp;utm_content=116585">SergePavkinMusic</a> from <a href="https://pixabay.com//?utm_source=link-attribution&amp;utm_medium=referral&amp;utm_campaign=music&amp;utm_content=116585">Pixabay</a>
 
 pub mod animations;
+mod audio_settings;
+mod beam_effects;
 mod block;
+mod config;
+mod controls;
+mod gamepad;
+mod generator;
+mod graphics_settings;
 mod hologramify;
 mod interface;
+mod level_format;
+mod level_validator;
+mod metrics;
+mod palette;
+mod practice;
+mod reference_scores;
+mod screens;
 mod setup;
 mod setup_complete;
 mod setup_menu;
 mod simulation;
+mod slot_picker;
+mod solution_code;
+mod solver;
+mod sound_effects;
 mod structure;
+mod tint;
 mod world;
 
 use animations::AnimationPlugin;
+use audio_settings::{AudioSettings, AudioSettingsPlugin, Music, MUSIC_VOLUME};
+use beam_effects::BeamEffectsPlugin;
 use bevy::{audio::AudioSink, prelude::*};
 use bevy_mod_raycast::{DefaultRaycastingPlugin, RaycastSystem};
 use bevy_obj::ObjPlugin;
 use block::{update_raycast_position_from_cursor, BlockRaycastSet};
+use controls::ControlsPlugin;
+use gamepad::GamepadPlugin;
+use graphics_settings::GraphicsSettingsPlugin;
 use hologramify::HologramifyPlugin;
 use interface::InterfacePlugin;
+use palette::PalettePlugin;
+use practice::PracticePlugin;
+use screens::ScreenPlugin;
 use setup::SetupPlugin;
 use setup_complete::CompletePlugin;
 use setup_menu::MenuPlugin;
 use simulation::SimulationPlugin;
+use slot_picker::SlotPickerPlugin;
+use sound_effects::SoundEffectsPlugin;
+use tint::TintPlugin;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum GameState {
@@ -41,10 +71,16 @@ fn setup_music(
     mut commands: Commands,
     assets: Res<AssetServer>,
     audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
+    sinks: Res<Assets<AudioSink>>,
     mut windows: ResMut<Windows>,
 ) {
     let music = assets.load("music.ogg");
-    audio.play_with_settings(music, PlaybackSettings::LOOP.with_volume(0.3));
+    let sink = audio.play_with_settings(
+        music,
+        PlaybackSettings::LOOP.with_volume(MUSIC_VOLUME * audio_settings.music_volume()),
+    );
+    commands.insert_resource(Music(sinks.get_handle(sink)));
     commands.insert_resource(Sfx {
         level_complete: assets.load("level_complete.ogg"),
         click: assets.load("click.ogg"),
@@ -59,23 +95,38 @@ fn setup_music(
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--convert-levels") {
+        level_format::convert_files(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("--solve") {
+        solver::solve_files(&args[1..]);
+        return;
+    }
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugin(ObjPlugin)
         .add_plugin(DefaultRaycastingPlugin::<BlockRaycastSet>::default())
         .add_state(GameState::Menu)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(AudioSettingsPlugin)
+        .add_plugin(GraphicsSettingsPlugin)
+        .add_plugin(ScreenPlugin)
+        .add_plugin(GamepadPlugin)
         .add_plugin(SimulationPlugin)
+        .add_plugin(SoundEffectsPlugin)
         .add_plugin(AnimationPlugin)
         .add_plugin(HologramifyPlugin)
+        .add_plugin(TintPlugin)
+        .add_plugin(PalettePlugin)
+        .add_plugin(BeamEffectsPlugin)
         .add_plugin(SetupPlugin)
         .add_plugin(InterfacePlugin)
         .add_plugin(MenuPlugin)
+        .add_plugin(SlotPickerPlugin)
+        .add_plugin(PracticePlugin)
         .add_plugin(CompletePlugin)
-        .insert_resource(AmbientLight {
-            color: Color::hex("264653").unwrap(),
-            brightness: 5.0,
-        })
-        .insert_resource(ClearColor(Color::hex("264653").unwrap() * 0.6))
         .add_startup_system(setup_music)
         .add_system_to_stage(
             CoreStage::First,
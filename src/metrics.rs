@@ -0,0 +1,118 @@
+//! Measurements of a solution beyond cycles, blocks and parts. These are only
+//! shown, `Score` stays as it was so older saves still read.
+
+use crate::{block::BlockKind, world::Part};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Metrics {
+    /// Area of the bounding box of the user parts, seen from above.
+    pub footprint: u32,
+    /// Volume of the same bounding box.
+    pub volume: u32,
+    pub tractor_sources: u32,
+    pub welder_sources: u32,
+    /// Summed length of every beam that pulled or welded, over every tick.
+    pub beam_distance: u32,
+}
+
+impl Metrics {
+    pub fn measure(user_parts: &[Part], beam_distance: f32) -> Self {
+        let blocks = || user_parts.iter().flat_map(|part| &part.structure.blocks);
+        let count = |kind| blocks().filter(|block| block.kind == kind).count() as u32;
+        let mut min = (i32::MAX, i32::MAX, i32::MAX);
+        let mut max = (i32::MIN, i32::MIN, i32::MIN);
+        for block in blocks() {
+            let (x, y, z) = block.position;
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        }
+        let (footprint, volume) = if blocks().next().is_none() {
+            (0, 0)
+        } else {
+            let size = |min: i32, max: i32| (max - min + 1) as u32;
+            let area = size(min.0, max.0) * size(min.1, max.1);
+            (area, area * size(min.2, max.2))
+        };
+        Self {
+            footprint,
+            volume,
+            tractor_sources: count(BlockKind::TractorBeamSource),
+            welder_sources: count(BlockKind::WelderBeamSource),
+            beam_distance: beam_distance.round() as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+
+    use super::*;
+    use crate::{
+        block::{Block, BlockFacing},
+        structure::Structure,
+        world::Position,
+    };
+
+    fn part(blocks: &[(BlockKind, Position)]) -> Part {
+        Part {
+            structure: Structure {
+                blocks: blocks
+                    .iter()
+                    .map(|&(kind, position)| Block {
+                        kind,
+                        facing: BlockFacing::Px,
+                        position,
+                    })
+                    .collect(),
+            },
+            physical_instance: Entity::from_raw(0),
+            is_hologram: false,
+        }
+    }
+
+    #[test]
+    fn nothing_measures_nothing() {
+        assert_eq!(Metrics::measure(&[], 0.4), Metrics::default());
+        assert_eq!(Metrics::measure(&[part(&[])], 0.0), Metrics::default());
+    }
+
+    #[test]
+    fn one_block_takes_one_cell() {
+        let metrics = Metrics::measure(&[part(&[(BlockKind::Structure, (4, -2, 1))])], 2.5);
+        assert_eq!(
+            metrics,
+            Metrics {
+                footprint: 1,
+                volume: 1,
+                tractor_sources: 0,
+                welder_sources: 0,
+                beam_distance: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn sources_are_counted_over_every_part() {
+        let parts = [
+            part(&[
+                (BlockKind::TractorBeamSource, (0, 0, 0)),
+                (BlockKind::Structure, (1, 0, 0)),
+            ]),
+            part(&[
+                (BlockKind::WelderBeamSource, (3, 2, 1)),
+                (BlockKind::TractorBeamSource, (3, 2, 0)),
+            ]),
+        ];
+        assert_eq!(
+            Metrics::measure(&parts, 7.6),
+            Metrics {
+                footprint: 12,
+                volume: 24,
+                tractor_sources: 2,
+                welder_sources: 1,
+                beam_distance: 8,
+            }
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::{pbr::NotShadowCaster, prelude::*};
+
+use crate::{
+    block::{BlockKind, BlockVisual},
+    graphics_settings::GraphicsSettings,
+    tint::Tint,
+};
+
+/// Colours for the things that are otherwise only told apart by colour. `None`
+/// keeps whatever colour the model was made with.
+pub struct Palette {
+    pub name: &'static str,
+    pub input: Option<Color>,
+    pub output: Option<Color>,
+    pub tractor_beam: Option<Color>,
+    pub welder_beam: Option<Color>,
+    /// Multiplied onto the hologram tint from the graphics settings.
+    pub hologram: Color,
+    pub ambient: Color,
+    pub ambient_brightness: f32,
+    pub clear: Color,
+}
+
+fn hex(code: &str) -> Option<Color> {
+    Some(Color::hex(code).unwrap())
+}
+
+pub fn palettes() -> [Palette; 5] {
+    let background = Color::hex("264653").unwrap();
+    [
+        Palette {
+            name: "default",
+            input: None,
+            output: None,
+            tractor_beam: None,
+            welder_beam: None,
+            hologram: Color::WHITE,
+            ambient: background,
+            ambient_brightness: 5.0,
+            clear: background * 0.6,
+        },
+        Palette {
+            name: "deuteranopia",
+            input: hex("0072B2"),
+            output: hex("E69F00"),
+            tractor_beam: hex("56B4E9"),
+            welder_beam: hex("F0E442"),
+            hologram: Color::hex("D0E8FF").unwrap(),
+            ambient: background,
+            ambient_brightness: 5.0,
+            clear: background * 0.6,
+        },
+        Palette {
+            name: "protanopia",
+            input: hex("0072B2"),
+            output: hex("F0E442"),
+            tractor_beam: hex("56B4E9"),
+            welder_beam: hex("E69F00"),
+            hologram: Color::hex("D0E8FF").unwrap(),
+            ambient: background,
+            ambient_brightness: 5.0,
+            clear: background * 0.6,
+        },
+        Palette {
+            name: "tritanopia",
+            input: hex("009E9E"),
+            output: hex("E0245E"),
+            tractor_beam: hex("7FE0E0"),
+            welder_beam: hex("FF8080"),
+            hologram: Color::hex("FFE0E8").unwrap(),
+            ambient: Color::hex("3A3A3A").unwrap(),
+            ambient_brightness: 5.0,
+            clear: Color::hex("222222").unwrap(),
+        },
+        Palette {
+            name: "high contrast",
+            input: hex("0050FF"),
+            output: hex("FFD000"),
+            tractor_beam: hex("FFFFFF"),
+            welder_beam: hex("FF2020"),
+            hologram: Color::rgb(1.3, 1.3, 1.3),
+            ambient: Color::WHITE,
+            ambient_brightness: 3.0,
+            clear: Color::BLACK,
+        },
+    ]
+}
+
+/// Floats above input and output tiles so they can be told apart by shape.
+#[derive(Component)]
+struct ShapeMarker;
+
+fn spawn_shape_marker(
+    commands: &mut Commands,
+    parent: Entity,
+    parent_rotation: Quat,
+    kind: BlockKind,
+    palette: &Palette,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let (mesh, color, rotation) = match kind {
+        BlockKind::DecoStructureInput => (
+            Mesh::from(shape::Icosphere {
+                radius: 0.18,
+                subdivisions: 2,
+            }),
+            palette.input,
+            Quat::IDENTITY,
+        ),
+        BlockKind::DecoStructureOutput => (
+            Mesh::from(shape::Cube { size: 0.3 }),
+            palette.output,
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_4),
+        ),
+        _ => return,
+    };
+    // Markers sit above the tile no matter which way the tile is facing.
+    let undo = parent_rotation.inverse();
+    let marker = commands
+        .spawn()
+        .insert_bundle(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color: color.unwrap_or(Color::WHITE),
+                unlit: true,
+                ..Default::default()
+            }),
+            transform: Transform {
+                translation: undo * Vec3::new(0.0, 0.0, 0.65),
+                rotation: undo * rotation,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(NotShadowCaster)
+        .insert(ShapeMarker)
+        .id();
+    commands.entity(parent).add_child(marker);
+}
+
+/// Recolours input and output tiles whenever the palette changes or new ones
+/// get spawned, and applies the palette's lighting. Beams are recoloured by
+/// `beam_effects`.
+fn palette_system(
+    mut commands: Commands,
+    settings: Res<GraphicsSettings>,
+    blocks: Query<(Entity, &BlockVisual, &Transform)>,
+    new_blocks: Query<(Entity, &BlockVisual, &Transform), Added<BlockVisual>>,
+    markers: Query<Entity, With<ShapeMarker>>,
+    mut ambient: ResMut<AmbientLight>,
+    mut clear: ResMut<ClearColor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let changed = settings.is_changed();
+    if !changed && new_blocks.is_empty() {
+        return;
+    }
+    let palette = settings.palette();
+    if changed {
+        ambient.color = palette.ambient;
+        ambient.brightness = palette.ambient_brightness;
+        clear.0 = palette.clear;
+        for marker in markers.iter() {
+            commands.entity(marker).despawn_recursive();
+        }
+    }
+    let recolor = |color: Option<Color>| Tint {
+        recolor: color,
+        ..Tint::new(Color::WHITE)
+    };
+    for (entity, visual, _) in blocks.iter() {
+        let tint = match visual.0 {
+            BlockKind::DecoStructureInput => recolor(palette.input),
+            BlockKind::DecoStructureOutput => recolor(palette.output),
+            _ => continue,
+        };
+        commands.entity(entity).insert(tint);
+    }
+    if !settings.shape_markers {
+        return;
+    }
+    let with_markers: Vec<_> = if changed {
+        blocks.iter().collect()
+    } else {
+        new_blocks.iter().collect()
+    };
+    for (entity, visual, transform) in with_markers {
+        spawn_shape_marker(
+            &mut commands,
+            entity,
+            transform.rotation,
+            visual.0,
+            &palette,
+            &mut *meshes,
+            &mut *materials,
+        );
+    }
+}
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(palette_system);
+    }
+}
@@ -0,0 +1,214 @@
+//! The corner of the menu for the generated practice level, where its seed
+//! can be typed in or rolled.
+
+use bevy::{
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+    window::ReceivedCharacter,
+};
+use rand::Rng;
+
+use crate::{
+    audio_settings::AudioSettings,
+    controls::{Action, ActionEvent},
+    gamepad::VirtualCursor,
+    setup_complete::absolute,
+    setup_menu::{GlobalState, MenuEntity, MenuRoot, PRACTICE_LEVEL},
+    slot_picker::{contains, spawn_box, SlotPicker},
+    GameState, Sfx,
+};
+
+#[derive(Component)]
+struct PracticePanel;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PracticeButton {
+    Play,
+    Seed,
+    Random,
+}
+
+const BUTTONS: [PracticeButton; 3] = [
+    PracticeButton::Play,
+    PracticeButton::Seed,
+    PracticeButton::Random,
+];
+
+/// Below level 10 in the bottom right, in percent of the window.
+fn button_rect(index: usize) -> (Vec2, Vec2) {
+    let top = 24.0 - 7.0 * index as f32;
+    (Vec2::new(81.0, top - 6.0), Vec2::new(99.0, top))
+}
+
+/// Seeds longer than this are not worth typing.
+const MAX_SEED_DIGITS: usize = 9;
+
+const BUTTON_COLOR: &str = "2A9D8F";
+const BUTTON_HOVER_COLOR: &str = "E9C46A";
+
+fn spawn_panel(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    global_state: &GlobalState,
+    editing: &Option<String>,
+    hovered: Option<usize>,
+) -> Entity {
+    let panel = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: absolute(0.0, 0.0, 100.0, 100.0),
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(MenuEntity)
+        .insert(PracticePanel)
+        .id();
+    for (index, button) in BUTTONS.into_iter().enumerate() {
+        let label = match (button, editing) {
+            (PracticeButton::Play, _) => "Practice".to_owned(),
+            (PracticeButton::Seed, Some(seed)) => format!("Seed {}_", seed),
+            (PracticeButton::Seed, None) => format!("Seed {}", global_state.practice_seed),
+            (PracticeButton::Random, _) => "Random seed".to_owned(),
+        };
+        let editing_this = button == PracticeButton::Seed && editing.is_some();
+        let color = if hovered == Some(index) || editing_this {
+            BUTTON_HOVER_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        let button = spawn_box(
+            commands,
+            assets,
+            button_rect(index),
+            Color::hex(color).unwrap(),
+            label,
+            true,
+        );
+        commands.entity(panel).add_child(button);
+    }
+    panel
+}
+
+fn practice_system(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut global_state: ResMut<GlobalState>,
+    slot_picker: Option<Res<SlotPicker>>,
+    roots: Query<Entity, With<MenuRoot>>,
+    panels: Query<Entity, With<PracticePanel>>,
+    windows: Res<Windows>,
+    virtual_cursor: Res<VirtualCursor>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut action_events: EventReader<ActionEvent>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut characters: EventReader<ReceivedCharacter>,
+    // The seed being typed in.
+    mut editing: Local<Option<String>>,
+    mut hovered: Local<Option<usize>>,
+    sfx: Res<Sfx>,
+    audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
+) {
+    let clicking = mouse_events
+        .iter()
+        .any(|e| e.button == MouseButton::Left && e.state == ButtonState::Pressed);
+    let actions: Vec<_> = action_events
+        .iter()
+        .filter(|e| e.pressed)
+        .map(|e| e.action)
+        .collect();
+    let deleting = keyboard_events
+        .iter()
+        .any(|e| e.state == ButtonState::Pressed && e.key_code == Some(KeyCode::Back));
+    let digits: String = characters
+        .iter()
+        .map(|e| e.char)
+        .filter(char::is_ascii_digit)
+        .collect();
+    let root = match roots.get_single() {
+        Ok(root) => root,
+        Err(_) => return,
+    };
+    let panel = panels.get_single().ok();
+    // The slot picker is drawn over the panel and takes the input.
+    if slot_picker.is_some() {
+        *editing = None;
+        return;
+    }
+
+    let clicking = clicking || actions.contains(&Action::Click);
+    let win = windows.get_primary().unwrap();
+    let cursor = virtual_cursor
+        .cursor_position(win)
+        .map(|x| x * 100.0 / Vec2::new(win.width(), win.height()));
+    let hovered_button = cursor
+        .and_then(|cursor| (0..BUTTONS.len()).find(|&index| contains(button_rect(index), cursor)));
+    let mut changed = panel.is_none() || *hovered != hovered_button;
+    let pressed = hovered_button
+        .filter(|_| clicking)
+        .map(|index| BUTTONS[index]);
+
+    let mut handled = false;
+    if let Some(seed) = &mut *editing {
+        changed |= !digits.is_empty() || deleting;
+        seed.push_str(&digits);
+        seed.truncate(MAX_SEED_DIGITS);
+        if deleting {
+            seed.pop();
+        }
+        let confirmed =
+            actions.contains(&Action::ConfirmStart) || pressed == Some(PracticeButton::Seed);
+        if confirmed {
+            if let Ok(seed) = seed.parse() {
+                global_state.set_practice_seed(seed);
+            }
+            *editing = None;
+            changed = true;
+            handled = true;
+        } else if actions.contains(&Action::Back) || (clicking && pressed.is_none()) {
+            *editing = None;
+            changed = true;
+        }
+    }
+    match pressed {
+        Some(button) if !handled => {
+            audio_settings.play(&*audio, &sfx.click, 0.3);
+            *editing = None;
+            changed = true;
+            match button {
+                PracticeButton::Play => {
+                    commands.insert_resource(SlotPicker::open(PRACTICE_LEVEL, &global_state));
+                }
+                PracticeButton::Seed => *editing = Some(String::new()),
+                PracticeButton::Random => {
+                    let seed = rand::thread_rng().gen_range(0..100000);
+                    global_state.set_practice_seed(seed);
+                }
+            }
+        }
+        _ => (),
+    }
+
+    if changed {
+        *hovered = hovered_button;
+        if let Some(panel) = panel {
+            commands.entity(panel).despawn_recursive();
+        }
+        let panel = spawn_panel(
+            &mut commands,
+            &assets,
+            &global_state,
+            &editing,
+            hovered_button,
+        );
+        commands.entity(root).add_child(panel);
+    }
+}
+
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Menu).with_system(practice_system));
+    }
+}
@@ -0,0 +1,134 @@
+//! Scores of reference solutions, shown on the complete screen so a solution
+//! can be compared against others.
+
+const REFERENCE_SCORES: &str = include_str!("../assets/levels/reference_scores.txt");
+
+/// Cycles, blocks and parts, the same as in `GlobalState::last`.
+pub type Score = (u32, u32, u32);
+
+pub fn reference_scores(level: usize) -> Vec<Score> {
+    parse_scores(REFERENCE_SCORES, level)
+}
+
+fn parse_scores(text: &str, level: usize) -> Vec<Score> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let numbers: Option<Vec<u32>> =
+                line.split_whitespace().map(|x| x.parse().ok()).collect();
+            match numbers.as_deref() {
+                // Anything after the parts, such as extra metrics, is ignored.
+                Some(&[for_level, cycles, blocks, parts, ..]) if for_level as usize == level => {
+                    Some((cycles, blocks, parts))
+                }
+                Some(&[_, _, _, _, ..]) => None,
+                _ => {
+                    println!("Ignoring \"{}\" in the reference scores", line);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub struct Histogram {
+    /// The lowest value that goes in the first bin.
+    pub start: u32,
+    pub bin_width: u32,
+    pub counts: Vec<u32>,
+}
+
+/// At most this many bins, fewer when the values are close together.
+const MAX_BINS: u32 = 12;
+
+impl Histogram {
+    /// Bins the reference values, making sure the range also covers `player`
+    /// so that it can be marked on the chart.
+    pub fn new(values: &[u32], player: u32) -> Self {
+        let min = values.iter().copied().fold(player, u32::min);
+        let max = values.iter().copied().fold(player, u32::max);
+        let bin_width = (max - min) / MAX_BINS + 1;
+        let bins = (max - min) / bin_width + 1;
+        let mut counts = vec![0; bins as usize];
+        for &value in values {
+            counts[((value - min) / bin_width) as usize] += 1;
+        }
+        Self {
+            start: min,
+            bin_width,
+            counts,
+        }
+    }
+
+    pub fn bin_of(&self, value: u32) -> usize {
+        ((value.max(self.start) - self.start) / self.bin_width) as usize
+    }
+
+    /// The value just past the last bin.
+    pub fn end(&self) -> u32 {
+        self.start + self.bin_width * self.counts.len() as u32
+    }
+}
+
+/// How many of the values are worse than `player`, lower being better.
+pub fn beaten_fraction(values: &[u32], player: u32) -> f32 {
+    if values.is_empty() {
+        return 1.0;
+    }
+    let beaten = values.iter().filter(|&&value| value > player).count();
+    beaten as f32 / values.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_read_for_one_level() {
+        let text = "# A comment\n\n0 40 8 1\n1 48 15 1\n  0 20 9 1 12 30 2 1 8\n0 oops\n";
+        assert_eq!(parse_scores(text, 0), vec![(40, 8, 1), (20, 9, 1)]);
+        assert_eq!(parse_scores(text, 1), vec![(48, 15, 1)]);
+        assert_eq!(parse_scores(text, 2), vec![]);
+    }
+
+    #[test]
+    fn shipped_scores_parse() {
+        assert!(!reference_scores(0).is_empty());
+    }
+
+    #[test]
+    fn the_range_covers_the_player_without_counting_them() {
+        let histogram = Histogram::new(&[10, 12, 12, 15], 20);
+        assert_eq!(histogram.start, 10);
+        assert_eq!(histogram.bin_width, 1);
+        assert_eq!(histogram.counts, vec![1, 0, 2, 0, 0, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(histogram.bin_of(20), 10);
+        assert_eq!(histogram.end(), 21);
+
+        let histogram = Histogram::new(&[100, 150, 300], 0);
+        assert_eq!(histogram.start, 0);
+        assert_eq!(histogram.bin_width, 26);
+        assert_eq!(histogram.counts.len(), 12);
+        assert_eq!(histogram.counts.iter().sum::<u32>(), 3);
+        assert_eq!(histogram.bin_of(300), 11);
+        assert!(histogram.end() > 300);
+    }
+
+    #[test]
+    fn without_values_only_the_player_is_binned() {
+        let histogram = Histogram::new(&[], 7);
+        assert_eq!(histogram.start, 7);
+        assert_eq!(histogram.counts, vec![0]);
+        assert_eq!(histogram.bin_of(7), 0);
+        assert_eq!(histogram.end(), 8);
+    }
+
+    #[test]
+    fn beaten_counts_strictly_worse_values() {
+        assert_eq!(beaten_fraction(&[10, 20, 30, 40], 20), 0.5);
+        assert_eq!(beaten_fraction(&[10, 20], 5), 1.0);
+        assert_eq!(beaten_fraction(&[10, 20], 50), 0.0);
+        assert_eq!(beaten_fraction(&[], 50), 1.0);
+    }
+}
@@ -0,0 +1,458 @@
+use bevy::{
+    ecs::event::Events,
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+};
+
+use crate::{
+    audio_settings::AudioSettings,
+    controls::{Action, ActionEvent, Binding, Bindings},
+    gamepad::BUTTON_ACTIONS,
+    graphics_settings::{GraphicsSettings, GRAPHICS_ROWS},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Screen {
+    Controls,
+    Audio,
+    Graphics,
+}
+
+/// A settings screen drawn over whatever state the game is in.
+#[derive(Default)]
+pub struct OpenScreen {
+    screen: Option<Screen>,
+    root: Option<Entity>,
+    selected: usize,
+    waiting_for_key: bool,
+    holding_control: bool,
+}
+
+impl OpenScreen {
+    pub fn is_open(&self) -> bool {
+        self.screen.is_some()
+    }
+
+    pub fn close(&mut self) {
+        self.screen = None;
+    }
+
+    pub fn toggle(&mut self, screen: Screen) {
+        if self.screen == Some(screen) {
+            self.screen = None;
+        } else {
+            self.screen = Some(screen);
+            self.selected = 0;
+            self.waiting_for_key = false;
+        }
+    }
+}
+
+fn controls_screen_keys(
+    event: &KeyboardInput,
+    open_screen: &mut OpenScreen,
+    bindings: &mut Bindings,
+) {
+    let key = match event.key_code {
+        Some(key) => key,
+        None => return,
+    };
+    if key == KeyCode::LControl || key == KeyCode::RControl {
+        open_screen.holding_control = event.state == ButtonState::Pressed;
+        return;
+    }
+    if event.state != ButtonState::Pressed {
+        return;
+    }
+    if open_screen.waiting_for_key {
+        // Keys that could not be saved are ignored, it keeps waiting for one
+        // that can.
+        if let Some(binding) = Binding::new(key, open_screen.holding_control) {
+            let action = bindings.0[open_screen.selected].0;
+            bindings.set(action, binding);
+            bindings.save();
+            open_screen.waiting_for_key = false;
+        }
+        return;
+    }
+    let count = bindings.0.len();
+    match key {
+        KeyCode::Up => open_screen.selected = (open_screen.selected + count - 1) % count,
+        KeyCode::Down => open_screen.selected = (open_screen.selected + 1) % count,
+        KeyCode::Return => open_screen.waiting_for_key = true,
+        KeyCode::Escape => open_screen.screen = None,
+        _ => {
+            if bindings.get(Action::ToggleControlsScreen).map(|x| x.key) == Some(key) {
+                open_screen.screen = None;
+            }
+        }
+    }
+}
+
+fn controls_screen_text(
+    assets: &AssetServer,
+    open_screen: &OpenScreen,
+    bindings: &Bindings,
+) -> Vec<TextSection> {
+    let font = assets.load("RobotoSlab-Regular.ttf");
+    let style = |font_size, color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+    let conflicts = bindings.conflicts();
+    let mut sections = vec![TextSection {
+        value: "Controls\n".to_owned(),
+        style: style(50.0, Color::WHITE),
+    }];
+    for (index, (action, binding)) in bindings.0.iter().enumerate() {
+        let conflicting: Vec<_> = conflicts
+            .iter()
+            .filter_map(|&(a, b)| {
+                if a == *action {
+                    Some(b)
+                } else if b == *action {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .map(|x| x.name())
+            .collect();
+        let selected = index == open_screen.selected;
+        let binding = if selected && open_screen.waiting_for_key {
+            "press a key...".to_owned()
+        } else {
+            binding.describe()
+        };
+        let mut line = format!("{}: {}", action.name(), binding);
+        if !conflicting.is_empty() {
+            line.push_str(&format!("  (conflicts with {})", conflicting.join(", ")));
+        }
+        line.push('\n');
+        let color = if !conflicting.is_empty() {
+            Color::hex("E76F51").unwrap()
+        } else if selected {
+            Color::hex("E9C46A").unwrap()
+        } else {
+            Color::WHITE
+        };
+        sections.push(TextSection {
+            value: line,
+            style: style(15.0, color),
+        });
+    }
+    sections.push(TextSection {
+        value: "\nUp/Down to choose, Enter to rebind, Escape to close.".to_owned(),
+        style: style(22.0, Color::rgba(1.0, 1.0, 1.0, 0.6)),
+    });
+    sections
+}
+
+const AUDIO_ROWS: usize = 6;
+
+fn audio_screen_keys(
+    event: &KeyboardInput,
+    open_screen: &mut OpenScreen,
+    settings: &mut AudioSettings,
+    bindings: &Bindings,
+) {
+    let key = match (event.state, event.key_code) {
+        (ButtonState::Pressed, Some(key)) => key,
+        _ => return,
+    };
+    let step = match key {
+        KeyCode::Left => -0.1,
+        KeyCode::Right => 0.1,
+        _ => 0.0,
+    };
+    let row = open_screen.selected;
+    match key {
+        KeyCode::Up => open_screen.selected = (row + AUDIO_ROWS - 1) % AUDIO_ROWS,
+        KeyCode::Down => open_screen.selected = (row + 1) % AUDIO_ROWS,
+        KeyCode::Left | KeyCode::Right | KeyCode::Return => {
+            let volume = match row {
+                0 => Some(&mut settings.master),
+                1 => Some(&mut settings.music),
+                2 => Some(&mut settings.effects),
+                _ => None,
+            };
+            if let Some(volume) = volume {
+                // Rounded so that repeated steps land exactly on 0 and 1.
+                *volume = ((*volume + step) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+            } else {
+                let muted = match row {
+                    3 => &mut settings.muted,
+                    4 => &mut settings.music_muted,
+                    _ => &mut settings.effects_muted,
+                };
+                *muted = !*muted;
+            }
+            settings.save();
+        }
+        KeyCode::Escape => open_screen.screen = None,
+        _ => {
+            if bindings.get(Action::ToggleAudioScreen).map(|x| x.key) == Some(key) {
+                open_screen.screen = None;
+            }
+        }
+    }
+}
+
+fn audio_screen_text(
+    assets: &AssetServer,
+    open_screen: &OpenScreen,
+    settings: &AudioSettings,
+) -> Vec<TextSection> {
+    let font = assets.load("RobotoSlab-Regular.ttf");
+    let style = |font_size, color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+    let volume = |x: f32| {
+        let filled = (x * 10.0).round() as usize;
+        format!(
+            "{}{} {}%",
+            "|".repeat(filled),
+            ".".repeat(10 - filled),
+            (x * 100.0).round()
+        )
+    };
+    let toggle = |x: bool| (if x { "muted" } else { "on" }).to_owned();
+    let rows = [
+        ("Master volume", volume(settings.master)),
+        ("Music volume", volume(settings.music)),
+        ("Effects volume", volume(settings.effects)),
+        ("Everything", toggle(settings.muted)),
+        ("Music", toggle(settings.music_muted)),
+        ("Effects", toggle(settings.effects_muted)),
+    ];
+    let mut sections = vec![TextSection {
+        value: "Audio\n".to_owned(),
+        style: style(50.0, Color::WHITE),
+    }];
+    for (index, (name, value)) in rows.into_iter().enumerate() {
+        let color = if index == open_screen.selected {
+            Color::hex("E9C46A").unwrap()
+        } else {
+            Color::WHITE
+        };
+        sections.push(TextSection {
+            value: format!("{}: {}\n", name, value),
+            style: style(24.0, color),
+        });
+    }
+    sections.push(TextSection {
+        value:
+            "\nUp/Down to choose, Left/Right to change the volume,\nEnter to mute, Escape to close."
+                .to_owned(),
+        style: style(22.0, Color::rgba(1.0, 1.0, 1.0, 0.6)),
+    });
+    sections
+}
+
+fn graphics_screen_keys(
+    event: &KeyboardInput,
+    open_screen: &mut OpenScreen,
+    settings: &mut GraphicsSettings,
+    bindings: &Bindings,
+) {
+    let key = match (event.state, event.key_code) {
+        (ButtonState::Pressed, Some(key)) => key,
+        _ => return,
+    };
+    let row = open_screen.selected;
+    match key {
+        KeyCode::Up => open_screen.selected = (row + GRAPHICS_ROWS - 1) % GRAPHICS_ROWS,
+        KeyCode::Down => open_screen.selected = (row + 1) % GRAPHICS_ROWS,
+        KeyCode::Left | KeyCode::Right => {
+            settings.adjust(row, if key == KeyCode::Left { -1 } else { 1 });
+            settings.save();
+        }
+        KeyCode::Back => {
+            *settings = GraphicsSettings::default();
+            settings.save();
+        }
+        KeyCode::Escape => open_screen.screen = None,
+        _ => {
+            if bindings.get(Action::ToggleGraphicsScreen).map(|x| x.key) == Some(key) {
+                open_screen.screen = None;
+            }
+        }
+    }
+}
+
+fn graphics_screen_text(
+    assets: &AssetServer,
+    open_screen: &OpenScreen,
+    settings: &GraphicsSettings,
+) -> Vec<TextSection> {
+    let font = assets.load("RobotoSlab-Regular.ttf");
+    let style = |font_size, color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+    let mut sections = vec![TextSection {
+        value: "Graphics\n".to_owned(),
+        style: style(50.0, Color::WHITE),
+    }];
+    for (index, (name, value)) in settings.rows().into_iter().enumerate() {
+        let color = if index == open_screen.selected {
+            Color::hex("E9C46A").unwrap()
+        } else {
+            Color::WHITE
+        };
+        sections.push(TextSection {
+            value: format!("{}: {}\n", name, value),
+            style: style(24.0, color),
+        });
+    }
+    sections.push(TextSection {
+        value: "\nUp/Down to choose, Left/Right to change,\nBackspace to reset, Escape to close."
+            .to_owned(),
+        style: style(22.0, Color::rgba(1.0, 1.0, 1.0, 0.6)),
+    });
+    sections
+}
+
+fn gamepad_layout_text(assets: &AssetServer) -> Vec<TextSection> {
+    let style = TextStyle {
+        font: assets.load("RobotoSlab-Regular.ttf"),
+        font_size: 17.0,
+        color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+    };
+    let mut layout = "Gamepad\n\nLeft stick: cursor\nRight stick: camera\n".to_owned();
+    for (button, action) in BUTTON_ACTIONS {
+        layout.push_str(&format!("{:?}: {}\n", button, action.name()));
+    }
+    vec![TextSection {
+        value: layout,
+        style,
+    }]
+}
+
+fn screen_system(
+    mut commands: Commands,
+    mut actions: EventReader<ActionEvent>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut open_screen: ResMut<OpenScreen>,
+    mut bindings: ResMut<Bindings>,
+    mut audio_settings: ResMut<AudioSettings>,
+    mut graphics_settings: ResMut<GraphicsSettings>,
+    assets: Res<AssetServer>,
+) {
+    if let Some(root) = open_screen.root.take() {
+        commands.entity(root).despawn_recursive();
+    }
+    // Raw key presses only go to screens that were already open, otherwise the
+    // key that opened a screen would immediately close it again.
+    for event in key_events.iter() {
+        match open_screen.screen {
+            Some(Screen::Controls) => {
+                controls_screen_keys(event, &mut *open_screen, &mut *bindings)
+            }
+            Some(Screen::Audio) => {
+                audio_screen_keys(event, &mut *open_screen, &mut *audio_settings, &*bindings)
+            }
+            Some(Screen::Graphics) => graphics_screen_keys(
+                event,
+                &mut *open_screen,
+                &mut *graphics_settings,
+                &*bindings,
+            ),
+            None => (),
+        }
+    }
+    for event in actions.iter() {
+        if !event.pressed {
+            continue;
+        }
+        match event.action {
+            Action::ToggleControlsScreen => open_screen.toggle(Screen::Controls),
+            Action::ToggleAudioScreen => open_screen.toggle(Screen::Audio),
+            Action::ToggleGraphicsScreen => open_screen.toggle(Screen::Graphics),
+            _ => (),
+        }
+    }
+    let columns = match open_screen.screen {
+        Some(Screen::Controls) => vec![
+            controls_screen_text(&*assets, &*open_screen, &*bindings),
+            gamepad_layout_text(&*assets),
+        ],
+        Some(Screen::Audio) => vec![audio_screen_text(&*assets, &*open_screen, &*audio_settings)],
+        Some(Screen::Graphics) => vec![graphics_screen_text(
+            &*assets,
+            &*open_screen,
+            &*graphics_settings,
+        )],
+        None => return,
+    };
+    let root = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                },
+                position: UiRect {
+                    left: Val::Percent(0.0),
+                    bottom: Val::Percent(0.0),
+                    ..Default::default()
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.85)),
+            ..Default::default()
+        })
+        .id();
+    for (index, sections) in columns.into_iter().enumerate() {
+        let text = commands
+            .spawn()
+            .insert_bundle(TextBundle {
+                text: Text {
+                    sections,
+                    alignment: TextAlignment::TOP_LEFT,
+                },
+                style: Style {
+                    position: UiRect {
+                        left: Val::Percent(5.0 + 55.0 * index as f32),
+                        top: Val::Percent(5.0),
+                        ..Default::default()
+                    },
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        commands.entity(root).add_child(text);
+    }
+    open_screen.root = Some(root);
+}
+
+/// Screens are only driven by keys, and clicks on them should not reach the
+/// level or menu underneath. The level and the menus read clicks in the "asdf"
+/// stage, which comes before `CoreStage::PreUpdate`, so clicks are dropped in
+/// `CoreStage::First`.
+fn drop_clicks_under_screens(
+    open_screen: Res<OpenScreen>,
+    mut mouse_events: ResMut<Events<MouseButtonInput>>,
+) {
+    if open_screen.is_open() {
+        mouse_events.clear();
+    }
+}
+
+pub struct ScreenPlugin;
+
+impl Plugin for ScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenScreen>()
+            .add_system(screen_system)
+            .add_system_to_stage(CoreStage::First, drop_clicks_under_screens);
+    }
+}
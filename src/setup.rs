@@ -13,13 +13,19 @@ use bevy::{
         texture::BevyDefault,
         view::RenderLayers,
     },
-    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
+    utils::{HashMap, HashSet},
+    window::WindowResized,
 };
 use bevy_mod_raycast::RayCastSource;
 
 use crate::{
-    block::BlockRaycastSet, interface::InterfaceState, setup_menu::GlobalState,
-    simulation::SimulationState, GameState,
+    block::BlockRaycastSet,
+    graphics_settings::{GraphicsSettings, HologramUniform},
+    interface::InterfaceState,
+    setup_menu::GlobalState,
+    simulation::SimulationState,
+    GameState,
 };
 
 pub fn setup(
@@ -30,6 +36,7 @@ pub fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<PostProcessMaterial>>,
     global_state: Res<GlobalState>,
+    graphics_settings: Res<GraphicsSettings>,
 ) {
     let (target, holo_target, size) = setup_render_targets(&mut commands, &*windows, &mut *images);
     setup_cameras(
@@ -39,6 +46,7 @@ pub fn setup(
         target,
         holo_target,
         size,
+        HologramUniform::new(&*graphics_settings, 0.0),
     );
     setup_light(&mut commands);
     let first_user_part = crate::world::setup::setup_world(&mut commands, &*assets, &*global_state);
@@ -50,6 +58,10 @@ pub fn setup(
         collected_outputs: 0,
         cycles: 0,
         speed: 1.0,
+        moving_parts: HashSet::new(),
+        blocked_parts: HashSet::new(),
+        beams: HashMap::new(),
+        beam_distance: 0.0,
     };
     crate::interface::setup::setup_interface_state(
         &mut commands,
@@ -61,17 +73,22 @@ pub fn setup(
     commands.insert_resource(sim_state);
 }
 
+fn window_size(windows: &Windows) -> Extent3d {
+    let window = windows.primary();
+    // A minimized window has no area, which textures are not allowed to have.
+    Extent3d {
+        width: window.physical_width().max(1),
+        height: window.physical_height().max(1),
+        ..Default::default()
+    }
+}
+
 fn setup_render_targets(
     commands: &mut Commands,
     windows: &Windows,
     images: &mut Assets<Image>,
 ) -> (Handle<Image>, Handle<Image>, Extent3d) {
-    let window = windows.primary();
-    let size = Extent3d {
-        width: window.physical_width(),
-        height: window.physical_height(),
-        ..Default::default()
-    };
+    let size = window_size(windows);
     let mut image1 = Image {
         texture_descriptor: TextureDescriptor {
             label: None,
@@ -98,6 +115,7 @@ fn setup_cameras(
     normal_render_target: Handle<Image>,
     holo_render_target: Handle<Image>,
     size: Extent3d,
+    hologram_settings: HologramUniform,
 ) {
     setup_main_camera(commands, normal_render_target.clone());
     setup_holographic_camera(commands, holo_render_target.clone());
@@ -108,6 +126,7 @@ fn setup_cameras(
         normal_render_target,
         holo_render_target,
         size,
+        hologram_settings,
     );
 }
 
@@ -157,6 +176,8 @@ pub struct PostProcessMaterial {
     #[texture(2)]
     #[sampler(3)]
     holo_render_target: Handle<Image>,
+    #[uniform(4)]
+    pub settings: HologramUniform,
 }
 
 impl Material2d for PostProcessMaterial {
@@ -172,15 +193,14 @@ fn setup_post_process_camera(
     render_target: Handle<Image>,
     holo_render_target: Handle<Image>,
     size: Extent3d,
+    settings: HologramUniform,
 ) {
     let post_processing_pass_layer = RenderLayers::layer((RenderLayers::TOTAL_LAYERS - 1) as u8);
-    let quad_handle = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-        size.width as f32,
-        size.height as f32,
-    ))));
+    let quad_handle = meshes.add(post_process_quad(size));
     let material_handle = materials.add(PostProcessMaterial {
         render_target,
         holo_render_target,
+        settings,
     });
     commands
         .spawn_bundle(MaterialMesh2dBundle {
@@ -207,6 +227,45 @@ fn setup_post_process_camera(
         .insert(post_processing_pass_layer);
 }
 
+fn post_process_quad(size: Extent3d) -> Mesh {
+    Mesh::from(shape::Quad::new(Vec2::new(
+        size.width as f32,
+        size.height as f32,
+    )))
+}
+
+/// Keeps both render targets and the quad showing them the same size as the
+/// window. The cameras pick up the new image size by themselves, which keeps
+/// raycasts from the cursor lined up with what is on screen.
+fn resize_render_targets(
+    mut resize_events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<Assets<PostProcessMaterial>>,
+    quads: Query<(&Mesh2dHandle, &Handle<PostProcessMaterial>)>,
+) {
+    let primary = windows.primary().id();
+    if !resize_events.iter().any(|event| event.id == primary) {
+        return;
+    }
+    let size = window_size(&*windows);
+    for (quad, material) in quads.iter() {
+        if let Some(material) = materials.get(material) {
+            for target in [&material.render_target, &material.holo_render_target] {
+                if let Some(image) = images.get_mut(target) {
+                    if image.texture_descriptor.size != size {
+                        image.resize(size);
+                    }
+                }
+            }
+        }
+        if let Some(mesh) = meshes.get_mut(&quad.0) {
+            *mesh = post_process_quad(size);
+        }
+    }
+}
+
 fn setup_light(commands: &mut Commands) {
     let tau8 = TAU / 8.0;
     commands
@@ -230,7 +289,9 @@ fn cleanup(
     mut commands: Commands,
     entities: Query<Entity, With<LevelEntity>>,
     interface_state: Res<InterfaceState>,
+    mut global_state: ResMut<GlobalState>,
 ) {
+    global_state.clipboard = interface_state.clipboard.clone();
     commands.remove_resource::<InterfaceState>();
     commands.remove_resource::<SimulationState>();
     commands.remove_resource::<World>();
@@ -253,6 +314,9 @@ impl Plugin for SetupPlugin {
             .add_system_set_to_stage(
                 "asdf",
                 SystemSet::on_exit(GameState::Level).with_system(cleanup),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Level).with_system(resize_render_targets),
             );
     }
 }
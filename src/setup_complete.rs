@@ -1,11 +1,15 @@
 use bevy::{
-    input::{keyboard::KeyboardInput, mouse::MouseButtonInput, ButtonState},
+    input::{mouse::MouseButtonInput, ButtonState},
     prelude::*,
 };
 use rand::{seq::SliceRandom, thread_rng, Rng};
 
 use crate::{
-    setup_menu::GlobalState,
+    audio_settings::AudioSettings,
+    controls::{Action, ActionEvent, Bindings},
+    gamepad::VirtualCursor,
+    reference_scores::{beaten_fraction, reference_scores, Histogram},
+    setup_menu::{ChangeToLevelRequest, GlobalState},
     world::{Position, World},
     GameState, Sfx,
 };
@@ -13,11 +17,372 @@ use crate::{
 #[derive(Component)]
 struct CompleteEntity;
 
-fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<GlobalState>) {
+pub fn absolute(left: f32, bottom: f32, width: f32, height: f32) -> Style {
+    Style {
+        position: UiRect {
+            left: Val::Percent(left),
+            bottom: Val::Percent(bottom),
+            ..Default::default()
+        },
+        size: Size {
+            width: Val::Percent(width),
+            height: Val::Percent(height),
+        },
+        position_type: PositionType::Absolute,
+        ..Default::default()
+    }
+}
+
+fn spawn_text(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    value: String,
+    font_size: f32,
+    left: f32,
+    bottom: f32,
+) -> Entity {
+    commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            text: Text::from_section(
+                value,
+                TextStyle {
+                    font: assets.load("RobotoSlab-Regular.ttf"),
+                    font_size,
+                    color: Color::WHITE,
+                },
+            ),
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(left),
+                    bottom: Val::Percent(bottom),
+                    ..Default::default()
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id()
+}
+
+/// A bar chart of how many reference solutions got each score, with a line
+/// where the player's score falls.
+fn spawn_histogram(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    parent: Entity,
+    left: f32,
+    name: &str,
+    values: &[u32],
+    player: u32,
+) {
+    let chart = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: absolute(left, 0.0, 30.0, 100.0),
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.25)),
+            ..Default::default()
+        })
+        .id();
+    commands.entity(parent).add_child(chart);
+    let beaten = (beaten_fraction(values, player) * 100.0).round();
+    let title = format!("{}: {} (better than {}%)", name, player, beaten);
+    let title = spawn_text(commands, assets, title, 22.0, 4.0, 84.0);
+    commands.entity(chart).add_child(title);
+
+    let histogram = Histogram::new(values, player);
+    let highest = histogram.counts.iter().copied().max().unwrap_or(0).max(1);
+    let bins = histogram.counts.len() as f32;
+    let player_bin = histogram.bin_of(player);
+    for (index, &count) in histogram.counts.iter().enumerate() {
+        let color = if index == player_bin {
+            Color::hex("E9C46A").unwrap()
+        } else {
+            Color::hex("2A9D8F").unwrap()
+        };
+        let bar = commands
+            .spawn()
+            .insert_bundle(NodeBundle {
+                style: absolute(
+                    5.0 + 90.0 * index as f32 / bins,
+                    18.0,
+                    90.0 / bins * 0.8,
+                    60.0 * count as f32 / highest as f32,
+                ),
+                color: UiColor(color),
+                ..Default::default()
+            })
+            .id();
+        commands.entity(chart).add_child(bar);
+    }
+    // Each value covers one unit of the range, the marker goes in its middle.
+    let range = (histogram.end() - histogram.start) as f32;
+    let marker_at = (player - histogram.start) as f32 + 0.5;
+    let marker = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: absolute(5.0 + 90.0 * marker_at / range - 0.4, 14.0, 0.8, 68.0),
+            color: UiColor(Color::hex("E76F51").unwrap()),
+            ..Default::default()
+        })
+        .id();
+    commands.entity(chart).add_child(marker);
+    let low = spawn_text(
+        commands,
+        assets,
+        histogram.start.to_string(),
+        18.0,
+        4.0,
+        3.0,
+    );
+    let high = (histogram.end() - 1).to_string();
+    let high = spawn_text(commands, assets, high, 18.0, 88.0, 3.0);
+    commands.entity(chart).push_children(&[low, high]);
+}
+
+/// Compares this run against the reference solutions for the level.
+fn spawn_histograms(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    parent: Entity,
+    level: usize,
+    (cycles, blocks, parts): (u32, u32, u32),
+) {
+    let charts = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: absolute(4.0, 13.0, 92.0, 24.0),
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(CompleteEntity)
+        .id();
+    commands.entity(parent).add_child(charts);
+    let references = reference_scores(level);
+    if references.is_empty() {
+        let text = "No reference solutions for this level yet.".to_owned();
+        let text = spawn_text(commands, assets, text, 30.0, 30.0, 40.0);
+        commands.entity(charts).add_child(text);
+        return;
+    }
+    let metrics = [
+        (
+            "Cycles",
+            references.iter().map(|x| x.0).collect::<Vec<_>>(),
+            cycles,
+        ),
+        ("Blocks", references.iter().map(|x| x.1).collect(), blocks),
+        ("Parts", references.iter().map(|x| x.2).collect(), parts),
+    ];
+    for (index, (name, values, player)) in metrics.iter().enumerate() {
+        let left = index as f32 * 35.0;
+        spawn_histogram(commands, assets, charts, left, name, values, *player);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CompleteAction {
+    ImproveSolution,
+    NextLevel,
+    Menu,
+}
+
+impl CompleteAction {
+    fn from_action(action: Action) -> Option<Self> {
+        match action {
+            Action::ImproveSolution => Some(Self::ImproveSolution),
+            Action::ConfirmStart => Some(Self::NextLevel),
+            Action::Back => Some(Self::Menu),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::ImproveSolution => "Improve solution",
+            Self::NextLevel => "Next level",
+            Self::Menu => "Menu",
+        }
+    }
+}
+
+const BUTTONS: [(CompleteAction, Action); 3] = [
+    (CompleteAction::ImproveSolution, Action::ImproveSolution),
+    (CompleteAction::NextLevel, Action::ConfirmStart),
+    (CompleteAction::Menu, Action::Back),
+];
+
+#[derive(Component)]
+struct CompleteButton {
+    action: CompleteAction,
+    /// Corners of the button, in percent of the window from the bottom left.
+    min: Vec2,
+    max: Vec2,
+    enabled: bool,
+}
+
+const BUTTON_COLOR: &str = "2A9D8F";
+const BUTTON_HOVER_COLOR: &str = "E9C46A";
+
+fn spawn_buttons(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    parent: Entity,
+    bindings: &Bindings,
+    global_state: &GlobalState,
+) {
+    let next_level = global_state.next_level(global_state.current_level);
+    for (index, (action, bound_to)) in BUTTONS.into_iter().enumerate() {
+        let enabled = action != CompleteAction::NextLevel || next_level.is_some();
+        let (left, bottom, width, height) = (12.0 + 27.0 * index as f32, 2.5, 22.0, 8.0);
+        let key = bindings
+            .get(bound_to)
+            .map(|x| format!(" ({})", x.describe()))
+            .unwrap_or_default();
+        let button = commands
+            .spawn()
+            .insert_bundle(NodeBundle {
+                style: Style {
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..absolute(left, bottom, width, height)
+                },
+                color: UiColor(if enabled {
+                    Color::hex(BUTTON_COLOR).unwrap()
+                } else {
+                    Color::rgba(1.0, 1.0, 1.0, 0.15)
+                }),
+                ..Default::default()
+            })
+            .insert(CompleteButton {
+                action,
+                min: Vec2::new(left, bottom),
+                max: Vec2::new(left + width, bottom + height),
+                enabled,
+            })
+            .with_children(|button| {
+                button.spawn_bundle(TextBundle {
+                    text: Text::from_section(
+                        format!("{}{}", action.label(), key),
+                        TextStyle {
+                            font: assets.load("RobotoSlab-Regular.ttf"),
+                            font_size: 26.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    ..Default::default()
+                });
+            })
+            .id();
+        commands.entity(parent).add_child(button);
+    }
+}
+
+fn complete_buttons(
+    mut commands: Commands,
+    mut buttons: Query<(&CompleteButton, &mut UiColor)>,
+    mut global_state: ResMut<GlobalState>,
+    windows: Res<Windows>,
+    virtual_cursor: Res<VirtualCursor>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut action_events: EventReader<ActionEvent>,
+    sfx: Res<Sfx>,
+    audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
+) {
+    let win = windows.get_primary().unwrap();
+    let cursor = virtual_cursor
+        .cursor_position(win)
+        .map(|x| x * 100.0 / Vec2::new(win.width(), win.height()));
+    // A click, from the mouse or the gamepad, picks whichever button is under
+    // the cursor.
+    let mut clicking = mouse_events
+        .iter()
+        .any(|e| e.button == MouseButton::Left && e.state == ButtonState::Pressed);
+    let mut chosen = None;
+    for event in action_events.iter().filter(|e| e.pressed) {
+        if event.action == Action::Click {
+            clicking = true;
+        } else if let Some(action) = CompleteAction::from_action(event.action) {
+            chosen = Some(action);
+        }
+    }
+    for (button, mut color) in buttons.iter_mut() {
+        if !button.enabled {
+            continue;
+        }
+        let hovered = cursor.map_or(false, |cursor| {
+            cursor.cmpge(button.min).all() && cursor.cmple(button.max).all()
+        });
+        color.0 = Color::hex(if hovered {
+            BUTTON_HOVER_COLOR
+        } else {
+            BUTTON_COLOR
+        })
+        .unwrap();
+        if hovered && clicking {
+            chosen = Some(button.action);
+        }
+    }
+    let next_level = global_state.next_level(global_state.current_level);
+    let action = match chosen {
+        Some(CompleteAction::NextLevel) if next_level.is_none() => return,
+        Some(action) => action,
+        None => return,
+    };
+    audio_settings.play(&*audio, &sfx.click, 0.3);
+    match action {
+        CompleteAction::ImproveSolution => commands.insert_resource(ChangeToLevelRequest),
+        CompleteAction::NextLevel => {
+            global_state.current_level = next_level.unwrap();
+            global_state.current_slot = 0;
+            commands.insert_resource(ChangeToLevelRequest);
+        }
+        CompleteAction::Menu => commands.insert_resource(ChangeToMenuRequest),
+    }
+}
+
+/// How much of the Pareto front fits on the screen.
+const SHOWN_SOLUTIONS: usize = 4;
+
+fn setup(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    global_state: Res<GlobalState>,
+    bindings: Res<Bindings>,
+) {
     let (this_cycles, this_blocks, this_parts) =
         global_state.last[global_state.current_level].unwrap();
-    let (high_cycles, high_blocks, high_parts) =
-        global_state.completed[global_state.current_level].unwrap();
+    let this_score = (this_cycles, this_blocks, this_parts);
+    // Every solution that is not beaten on all three metrics by another one.
+    let front = &global_state.completed[global_state.current_level];
+    let mut best = String::new();
+    for entry in front.iter().take(SHOWN_SOLUTIONS) {
+        let (cycles, blocks, parts) = entry.score;
+        best += &format!("{} Cycles, {} Blocks, {} Parts", cycles, blocks, parts);
+        // Solutions no slot holds any more can be restored from the picker.
+        let slot = global_state.slot_holding(global_state.current_level, &entry.solution);
+        best += &match slot {
+            _ if entry.score == this_score => "  (this run)\n".to_owned(),
+            Some(slot) => format!("  ({})\n", slot.name),
+            None => "  (not kept)\n".to_owned(),
+        };
+    }
+    let metrics = match global_state.last_metrics[global_state.current_level] {
+        Some(metrics) => format!(
+            "Footprint {}, Volume {}, {} Tractor and {} Welder Beams, Beam Distance {}\n",
+            metrics.footprint,
+            metrics.volume,
+            metrics.tractor_sources,
+            metrics.welder_sources,
+            metrics.beam_distance
+        ),
+        None => String::new(),
+    };
+    if front.len() > SHOWN_SOLUTIONS {
+        best += &format!("and {} more\n", front.len() - SHOWN_SOLUTIONS);
+    }
     commands
         .spawn()
         .insert_bundle(Camera2dBundle::default())
@@ -72,7 +437,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
                 justify_content: JustifyContent::Center,
                 position: UiRect {
                     left: Val::Percent(0.0),
-                    bottom: Val::Percent(20.0),
+                    bottom: Val::Percent(40.0),
                     ..Default::default()
                 },
                 size: Size {
@@ -96,15 +461,15 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
                         value: format!("Level Complete!\n"),
                         style: TextStyle {
                             font: assets.load("RobotoSlab-Regular.ttf"),
-                            font_size: 150.0,
+                            font_size: 100.0,
                             color: Color::WHITE,
                         },
                     },
                     TextSection {
-                        value: format!("Scores:\n"),
+                        value: format!("Scores for {}:\n", global_state.slot().name),
                         style: TextStyle {
                             font: assets.load("RobotoSlab-Regular.ttf"),
-                            font_size: 60.0,
+                            font_size: 40.0,
                             color: Color::WHITE,
                         },
                     },
@@ -115,35 +480,32 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
                         ),
                         style: TextStyle {
                             font: assets.load("RobotoSlab-Regular.ttf"),
-                            font_size: 60.0,
+                            font_size: 40.0,
                             color: Color::WHITE,
                         },
                     },
                     TextSection {
-                        value: format!("Your High Scores:\n"),
+                        value: metrics,
                         style: TextStyle {
                             font: assets.load("RobotoSlab-Regular.ttf"),
-                            font_size: 60.0,
-                            color: Color::WHITE,
+                            font_size: 26.0,
+                            color: Color::rgba(1.0, 1.0, 1.0, 0.7),
                         },
                     },
                     TextSection {
-                        value: format!(
-                            "{} Cycles, {} Blocks, {} Parts.\n",
-                            high_cycles, high_blocks, high_parts
-                        ),
+                        value: format!("Your Best Solutions:\n"),
                         style: TextStyle {
                             font: assets.load("RobotoSlab-Regular.ttf"),
-                            font_size: 90.0,
+                            font_size: 40.0,
                             color: Color::WHITE,
                         },
                     },
                     TextSection {
-                        value: format!("Press any key to continue."),
+                        value: best,
                         style: TextStyle {
                             font: assets.load("RobotoSlab-Regular.ttf"),
-                            font_size: 60.0,
-                            color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+                            font_size: 32.0,
+                            color: Color::WHITE,
                         },
                     },
                 ],
@@ -154,6 +516,14 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
         .insert(CompleteEntity)
         .id();
     commands.entity(t1_box).add_child(t1);
+    spawn_histograms(
+        &mut commands,
+        &*assets,
+        bg,
+        global_state.current_level,
+        (this_cycles, this_blocks, this_parts),
+    );
+    spawn_buttons(&mut commands, &*assets, bg, &*bindings, &*global_state);
     for i in 0..1000 {
         let mut colors = [
             Color::hex("2A9D8F").unwrap(),
@@ -198,15 +568,7 @@ struct Confetti {
     pub mass: f32,
 }
 
-fn simulate_confetti(
-    mut commands: Commands,
-    mut confetti: Query<(&mut Style, &mut Confetti)>,
-    time: Res<Time>,
-    mut key_events: EventReader<KeyboardInput>,
-    mut mouse_events: EventReader<MouseButtonInput>,
-    sfx: Res<Sfx>,
-    audio: Res<Audio>,
-) {
+fn simulate_confetti(mut confetti: Query<(&mut Style, &mut Confetti)>, time: Res<Time>) {
     for (mut style, mut confetti) in confetti.iter_mut() {
         let dx = confetti.vel * time.delta_seconds() * 2.0;
         confetti.pos += dx;
@@ -216,12 +578,6 @@ fn simulate_confetti(
         style.position.left = Val::Percent(confetti.pos.x);
         style.position.bottom = Val::Percent(confetti.pos.y);
     }
-    if key_events.iter().any(|e| e.state == ButtonState::Pressed)
-        || mouse_events.iter().any(|e| e.state == ButtonState::Pressed)
-    {
-        audio.play_with_settings(sfx.click.clone(), PlaybackSettings::ONCE.with_volume(0.3));
-        commands.insert_resource(ChangeToMenuRequest);
-    }
 }
 
 fn cleanup(mut commands: Commands, entities: Query<Entity, With<CompleteEntity>>) {
@@ -254,7 +610,9 @@ impl Plugin for CompletePlugin {
         )
         .add_system_set_to_stage(
             "asdf",
-            SystemSet::on_update(GameState::Complete).with_system(simulate_confetti),
+            SystemSet::on_update(GameState::Complete)
+                .with_system(simulate_confetti)
+                .with_system(complete_buttons),
         )
         .add_system_set_to_stage(
             "asdf",
@@ -2,20 +2,129 @@ use bevy::{
     input::{mouse::MouseButtonInput, ButtonState},
     prelude::*,
 };
+use rand::Rng;
 
 use crate::{
+    audio_settings::AudioSettings,
+    controls::{Action, ActionEvent, Bindings},
+    gamepad::VirtualCursor,
+    generator::generate,
+    level_format::write_level,
+    metrics::Metrics,
+    reference_scores::Score,
+    slot_picker::SlotPicker,
+    structure::Structure,
     world::{Position, World},
     GameState, Sfx,
 };
 
+/// Whether `a` is at least as good as `b` on every metric and better on one.
+fn dominates(a: Score, b: Score) -> bool {
+    a.0 <= b.0 && a.1 <= b.1 && a.2 <= b.2 && a != b
+}
+
+/// A completed run and the level, user parts included, that produced it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScoredSolution {
+    pub score: Score,
+    /// Written the same way as `SolutionSlot::level`.
+    pub solution: String,
+}
+
+/// Adds an entry to a Pareto front sorted by cycles, unless a score at least
+/// as good on every metric is already on it. Entries it beats are dropped
+/// along with their solutions. Returns whether it was added.
+fn add_to_front(front: &mut Vec<ScoredSolution>, entry: ScoredSolution) -> bool {
+    if front
+        .iter()
+        .any(|existing| existing.score == entry.score || dominates(existing.score, entry.score))
+    {
+        return false;
+    }
+    front.retain(|existing| !dominates(entry.score, existing.score));
+    front.push(entry);
+    front.sort_by_key(|existing| existing.score);
+    true
+}
+
+/// One of the machines the player keeps for a level, so that for example a
+/// fast one and a small one can be worked on side by side.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SolutionSlot {
+    pub name: String,
+    /// The level as it was last left, user parts included.
+    pub level: String,
+    pub best: Option<Score>,
+}
+
+impl SolutionSlot {
+    /// Keeps the new score unless the old one is at least as good on every
+    /// metric.
+    pub fn record(&mut self, score: Score) {
+        match self.best {
+            Some(best) if best == score || dominates(best, score) => (),
+            _ => self.best = Some(score),
+        }
+    }
+}
+
+/// How many solutions a level can have.
+pub const MAX_SLOTS: usize = 8;
+
+/// The generated level, after the hand made ones.
+pub const PRACTICE_LEVEL: usize = 10;
+
 pub struct GlobalState {
     pub current_level: usize,
-    pub last: [Option<(u32, u32, u32)>; 10],
-    pub completed: [Option<(u32, u32, u32)>; 10],
+    /// Index into `slots[current_level]`.
+    pub current_slot: usize,
+    pub last: [Option<Score>; PRACTICE_LEVEL + 1],
+    /// Measured alongside `last`.
+    pub last_metrics: [Option<Metrics>; PRACTICE_LEVEL + 1],
+    /// The Pareto front of every level, sorted by cycles. Empty until the
+    /// level has been completed.
+    pub completed: Vec<Vec<ScoredSolution>>,
+    /// The levels as they come, without any user parts.
     pub levels: Vec<String>,
+    /// Never empty for any level.
+    pub slots: Vec<Vec<SolutionSlot>>,
+    /// Kept here so that copied blocks can be pasted into other levels.
+    pub clipboard: Option<Structure>,
+    /// Whether to ask before starting a simulation that will split parts.
+    pub confirm_splits: bool,
+    /// What `levels[PRACTICE_LEVEL]` was generated from.
+    pub practice_seed: u64,
 }
 
 impl GlobalState {
+    /// Starts out with one empty solution for each of the hand made levels
+    /// and the practice level made from `practice_seed`.
+    pub fn new(mut levels: Vec<String>, practice_seed: u64) -> Self {
+        levels.push(write_level(&generate(practice_seed)));
+        let slots = levels
+            .iter()
+            .map(|level| {
+                vec![SolutionSlot {
+                    name: "Solution 1".to_owned(),
+                    level: level.clone(),
+                    best: None,
+                }]
+            })
+            .collect();
+        Self {
+            current_level: 0,
+            current_slot: 0,
+            last: [None; PRACTICE_LEVEL + 1],
+            last_metrics: [None; PRACTICE_LEVEL + 1],
+            completed: vec![Vec::new(); PRACTICE_LEVEL + 1],
+            levels,
+            slots,
+            clipboard: None,
+            confirm_splits: true,
+            practice_seed,
+        }
+    }
+
     pub fn unlocked(&self, index: usize) -> bool {
         let requirements = match index {
             0 => vec![],
@@ -28,22 +137,164 @@ impl GlobalState {
             7 => vec![5],
             8 => vec![5],
             9 => vec![7],
+            PRACTICE_LEVEL => vec![],
             _ => panic!(),
         };
-        requirements
-            .iter()
-            .all(|&req| self.completed[req].is_some())
+        requirements.iter().all(|&req| self.is_completed(req))
+    }
+
+    /// The solution being played.
+    pub fn slot(&self) -> &SolutionSlot {
+        &self.slots[self.current_level][self.current_slot]
+    }
+
+    pub fn slot_mut(&mut self) -> &mut SolutionSlot {
+        &mut self.slots[self.current_level][self.current_slot]
+    }
+
+    /// Adds an empty solution to the level and returns its index.
+    pub fn new_slot(&mut self, level: usize) -> usize {
+        let slots = &self.slots[level];
+        let name = (1..)
+            .map(|number| format!("Solution {}", number))
+            .find(|name| slots.iter().all(|slot| &slot.name != name))
+            .unwrap();
+        self.slots[level].push(SolutionSlot {
+            name,
+            level: self.levels[level].clone(),
+            best: None,
+        });
+        self.slots[level].len() - 1
+    }
+
+    /// Copies a solution and returns the index of the copy.
+    pub fn duplicate_slot(&mut self, level: usize, index: usize) -> usize {
+        let mut copy = self.slots[level][index].clone();
+        copy.name = format!("{} copy", copy.name);
+        self.slots[level].insert(index + 1, copy);
+        if level == self.current_level && self.current_slot > index {
+            self.current_slot += 1;
+        }
+        index + 1
+    }
+
+    /// Removes a solution, unless it is the only one left.
+    pub fn delete_slot(&mut self, level: usize, index: usize) {
+        if self.slots[level].len() <= 1 {
+            return;
+        }
+        self.slots[level].remove(index);
+        if level == self.current_level && self.current_slot >= index {
+            self.current_slot = self.current_slot.saturating_sub(1);
+        }
+    }
+
+    /// Replaces the practice level with the one made from `seed`. Its
+    /// solutions and scores go with it.
+    pub fn set_practice_seed(&mut self, seed: u64) {
+        let level = write_level(&generate(seed));
+        self.practice_seed = seed;
+        self.slots[PRACTICE_LEVEL] = vec![SolutionSlot {
+            name: "Solution 1".to_owned(),
+            level: level.clone(),
+            best: None,
+        }];
+        self.levels[PRACTICE_LEVEL] = level;
+        self.completed[PRACTICE_LEVEL].clear();
+        self.last[PRACTICE_LEVEL] = None;
+        self.last_metrics[PRACTICE_LEVEL] = None;
+        if self.current_level == PRACTICE_LEVEL {
+            self.current_slot = 0;
+        }
+    }
+
+    pub fn is_completed(&self, index: usize) -> bool {
+        !self.completed[index].is_empty()
+    }
+
+    /// Adds a result and the solution that got it to the level's Pareto
+    /// front. Returns whether it made it on.
+    pub fn record_completion(&mut self, level: usize, score: Score, solution: String) -> bool {
+        add_to_front(
+            &mut self.completed[level],
+            ScoredSolution { score, solution },
+        )
+    }
+
+    /// The solution slot of the level holding exactly this solution, if any.
+    pub fn slot_holding(&self, level: usize, solution: &str) -> Option<&SolutionSlot> {
+        self.slots[level].iter().find(|slot| slot.level == solution)
+    }
+
+    /// Whether `restore_front` would add anything.
+    pub fn can_restore_front(&self, level: usize) -> bool {
+        self.slots[level].len() < MAX_SLOTS
+            && self.completed[level]
+                .iter()
+                .any(|entry| self.slot_holding(level, &entry.solution).is_none())
+    }
+
+    /// Adds a slot for every solution on the level's front that is not kept in
+    /// one already, as long as there is room, fastest first. Returns the index
+    /// of the first one added.
+    pub fn restore_front(&mut self, level: usize) -> Option<usize> {
+        let mut first = None;
+        for entry in self.completed[level].clone() {
+            if self.slots[level].len() >= MAX_SLOTS {
+                break;
+            }
+            if self.slot_holding(level, &entry.solution).is_some() {
+                continue;
+            }
+            let (cycles, blocks, parts) = entry.score;
+            self.slots[level].push(SolutionSlot {
+                name: format!("Best {}c {}b {}p", cycles, blocks, parts),
+                level: entry.solution,
+                best: Some(entry.score),
+            });
+            first.get_or_insert(self.slots[level].len() - 1);
+        }
+        first
+    }
+
+    /// The level to go on to after `after`, preferring ones that have not been
+    /// completed yet. Never the practice level.
+    pub fn next_level(&self, after: usize) -> Option<usize> {
+        let count = PRACTICE_LEVEL;
+        // From the practice level every hand made level is a candidate, in
+        // order.
+        let (first, offsets) = if after >= count {
+            (0, 0..count)
+        } else {
+            (after, 1..count)
+        };
+        let others = || offsets.clone().map(move |offset| (first + offset) % count);
+        others()
+            .find(|&index| self.unlocked(index) && !self.is_completed(index))
+            .or_else(|| others().find(|&index| index > after && self.unlocked(index)))
     }
 }
 
 struct MenuState {
     hovers: Vec<(Entity, f32)>,
+    /// Lists the Pareto front of the level under the cursor.
+    tooltip: Entity,
+    tooltip_text: Entity,
 }
 
 #[derive(Component)]
-struct MenuEntity;
+pub struct MenuEntity;
 
-fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<GlobalState>) {
+/// The node everything on the menu is put in.
+#[derive(Component)]
+pub struct MenuRoot;
+
+fn setup(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    global_state: Res<GlobalState>,
+    bindings: Res<Bindings>,
+) {
     commands
         .spawn()
         .insert_bundle(Camera2dBundle::default())
@@ -67,6 +318,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
             ..Default::default()
         })
         .insert(MenuEntity)
+        .insert(MenuRoot)
         .id();
     let bg = commands
         .spawn()
@@ -95,7 +347,40 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
         .insert(MenuEntity)
         .id();
     commands.entity(root).add_child(bg);
-    let mut state = MenuState { hovers: Vec::new() };
+    let tooltip_text = commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: assets.load("RobotoSlab-Regular.ttf"),
+                    font_size: 22.0,
+                    color: Color::WHITE,
+                },
+            ),
+            ..Default::default()
+        })
+        .id();
+    let tooltip = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(MenuEntity)
+        .add_child(tooltip_text)
+        .id();
+    let mut state = MenuState {
+        hovers: Vec::new(),
+        tooltip,
+        tooltip_text,
+    };
     for index in 0..10 {
         let hover = assets.load(&format!("menu/l{}.png", index));
         let ent = commands
@@ -127,7 +412,12 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
             .id();
         commands.entity(root).add_child(ent);
         state.hovers.push((ent, 0.0));
-        if let Some((cycles, blocks, parts)) = global_state.completed[index] {
+        // The fastest solution, the tooltip shows the others.
+        if let Some(&ScoredSolution {
+            score: (cycles, blocks, parts),
+            ..
+        }) = global_state.completed[index].first()
+        {
             let pos = [
                 (19.0, 77.0),
                 (15.5, 51.5),
@@ -230,25 +520,71 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>, global_state: Res<Glo
             .id();
         commands.entity(root).add_child(ent);
     }
+    let describe = |action| {
+        bindings
+            .get(action)
+            .map(|x| x.describe())
+            .unwrap_or_default()
+    };
+    let hint = commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: format!(
+                        "{}: controls    {}: audio    {}: graphics",
+                        describe(Action::ToggleControlsScreen),
+                        describe(Action::ToggleAudioScreen),
+                        describe(Action::ToggleGraphicsScreen)
+                    ),
+                    style: TextStyle {
+                        font: assets.load("RobotoSlab-Regular.ttf"),
+                        font_size: 20.0,
+                        color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+                    },
+                }],
+                ..Default::default()
+            },
+            style: Style {
+                position: UiRect {
+                    left: Val::Percent(1.0),
+                    bottom: Val::Percent(1.0),
+                    ..Default::default()
+                },
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+    commands.entity(root).add_child(hint);
+    // Added last so that it is drawn over everything else.
+    commands.entity(root).add_child(tooltip);
     commands.insert_resource(state);
 }
 
 fn update_menu(
     mut commands: Commands,
     mut hovers: Query<&mut UiColor>,
+    mut tooltips: Query<(&mut Style, &mut Visibility)>,
+    mut texts: Query<&mut Text>,
     mut menu_state: ResMut<MenuState>,
-    mut global_state: ResMut<GlobalState>,
+    global_state: Res<GlobalState>,
     time: Res<Time>,
     windows: Res<Windows>,
+    virtual_cursor: Res<VirtualCursor>,
     mut mouse_button_events: EventReader<MouseButtonInput>,
+    mut action_events: EventReader<ActionEvent>,
+    slot_picker: Option<Res<SlotPicker>>,
     sfx: Res<Sfx>,
     audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
 ) {
     let d = time.delta_seconds() * 4.0;
     let win = windows.get_primary().unwrap();
     let width = win.width();
-    let mouse_pos = win
-        .cursor_position()
+    let mouse_pos = virtual_cursor
+        .cursor_position(win)
         .map(|x| x * 1920.0 / width)
         .unwrap_or(Vec2::new(-1000.0, -1000.0));
     let positions = [
@@ -265,7 +601,14 @@ fn update_menu(
     ];
     let mouse_pressed = mouse_button_events
         .iter()
-        .any(|e| e.button == MouseButton::Left && e.state == ButtonState::Pressed);
+        .any(|e| e.button == MouseButton::Left && e.state == ButtonState::Pressed)
+        | action_events
+            .iter()
+            .any(|e| e.action == Action::Click && e.pressed);
+    // The levels behind the slot picker cannot be picked.
+    let picking = slot_picker.is_some();
+    let mouse_pressed = mouse_pressed && !picking;
+    let mut hovered = None;
     for (index, (entity, opacity)) in menu_state.hovers.iter_mut().enumerate() {
         let size = 0.17 * width;
         let start = positions[index] - Vec2::new(0.0, size);
@@ -273,12 +616,12 @@ fn update_menu(
         if mouse_pos.cmpge(start).all()
             && mouse_pos.cmple(end).all()
             && global_state.unlocked(index)
+            && !picking
         {
+            hovered = Some(index);
             if mouse_pressed {
-                commands.insert_resource(ChangeToLevelRequest);
-                global_state.current_level = index;
-                audio
-                    .play_with_settings(sfx.click.clone(), PlaybackSettings::ONCE.with_volume(0.3));
+                commands.insert_resource(SlotPicker::open(index, &global_state));
+                audio_settings.play(&*audio, &sfx.click, 0.3);
             }
             *opacity += d;
         } else {
@@ -287,10 +630,28 @@ fn update_menu(
         *opacity = (*opacity).clamp(0.0, 1.0);
         hovers.get_mut(*entity).unwrap().0 = Color::rgba(1.0, 1.0, 1.0, *opacity);
     }
+    let (mut style, mut visibility) = tooltips.get_mut(menu_state.tooltip).unwrap();
+    let front = hovered.map_or(&[][..], |index| &global_state.completed[index][..]);
+    visibility.is_visible = !front.is_empty();
+    let cursor = virtual_cursor.cursor_position(win);
+    if let Some(cursor) = cursor.filter(|_| !front.is_empty()) {
+        let mut value = "Best solutions:".to_owned();
+        for &ScoredSolution {
+            score: (cycles, blocks, parts),
+            ..
+        } in front
+        {
+            value += &format!("\n{}c  {}b  {}p", cycles, blocks, parts);
+        }
+        texts.get_mut(menu_state.tooltip_text).unwrap().sections[0].value = value;
+        style.position.left = Val::Percent(cursor.x / width * 100.0 + 1.5);
+        style.position.bottom = Val::Percent(cursor.y / win.height() * 100.0);
+    }
 }
 
 fn cleanup(mut commands: Commands, entities: Query<Entity, With<MenuEntity>>) {
     commands.remove_resource::<MenuState>();
+    commands.remove_resource::<SlotPicker>();
     for entity in entities.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -325,13 +686,9 @@ impl Plugin for MenuPlugin {
             include_str!("../assets/levels/8.level.txt"),
             include_str!("../assets/levels/9.level.txt"),
         ];
+        let practice_seed = rand::thread_rng().gen_range(0..100000);
         let levels = levels.iter().copied().map(str::to_owned).collect();
-        app.insert_resource(GlobalState {
-            current_level: 0,
-            last: [None; 10],
-            completed: [None; 10],
-            levels,
-        });
+        app.insert_resource(GlobalState::new(levels, practice_seed));
         app.add_system_to_stage(CoreStage::First, set_state);
         app.add_system_set_to_stage(
             "asdf",
@@ -344,3 +701,104 @@ impl Plugin for MenuPlugin {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domination_needs_one_better_metric() {
+        assert!(dominates((10, 5, 1), (12, 5, 1)));
+        assert!(dominates((10, 4, 1), (10, 5, 2)));
+        assert!(!dominates((10, 5, 1), (10, 5, 1)));
+        assert!(!dominates((10, 6, 1), (12, 5, 1)));
+    }
+
+    fn entry(score: Score) -> ScoredSolution {
+        ScoredSolution {
+            score,
+            solution: format!("{:?}", score),
+        }
+    }
+
+    fn scores(front: &[ScoredSolution]) -> Vec<Score> {
+        front.iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn front_keeps_only_undominated_scores_in_order() {
+        let mut front = Vec::new();
+        assert!(add_to_front(&mut front, entry((40, 10, 2))));
+        assert!(add_to_front(&mut front, entry((20, 30, 2))));
+        assert!(add_to_front(&mut front, entry((60, 5, 1))));
+        assert_eq!(scores(&front), vec![(20, 30, 2), (40, 10, 2), (60, 5, 1)]);
+
+        // Equal and dominated scores are turned away.
+        assert!(!add_to_front(&mut front, entry((40, 10, 2))));
+        assert!(!add_to_front(&mut front, entry((41, 10, 2))));
+        assert_eq!(front.len(), 3);
+
+        // A better one pushes out everything it beats.
+        assert!(add_to_front(&mut front, entry((20, 10, 2))));
+        assert_eq!(front, vec![entry((20, 10, 2)), entry((60, 5, 1))]);
+    }
+
+    #[test]
+    fn dominated_solutions_leave_the_front_with_their_scores() {
+        let mut state = state();
+        state.record_completion(0, (40, 10, 2), "slow".to_owned());
+        state.record_completion(0, (20, 10, 2), "fast".to_owned());
+        assert_eq!(
+            state.completed[0],
+            vec![ScoredSolution {
+                score: (20, 10, 2),
+                solution: "fast".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn front_solutions_are_restored_into_new_slots_once() {
+        let mut state = state();
+        state.record_completion(0, (20, 30, 2), "fast".to_owned());
+        state.record_completion(0, (40, 10, 2), "small".to_owned());
+        state.slots[0][0].level = "small".to_owned();
+        assert!(state.can_restore_front(0));
+        assert_eq!(state.restore_front(0), Some(1));
+        assert_eq!(state.slots[0].len(), 2);
+        assert_eq!(state.slots[0][1].level, "fast");
+        assert_eq!(state.slots[0][1].best, Some((20, 30, 2)));
+        assert!(!state.can_restore_front(0));
+        assert_eq!(state.restore_front(0), None);
+    }
+
+    fn state() -> GlobalState {
+        GlobalState::new(vec![String::new(); PRACTICE_LEVEL], 1)
+    }
+
+    #[test]
+    fn copying_keeps_the_current_slot() {
+        let mut state = state();
+        state.current_level = 3;
+        state.new_slot(3);
+        state.new_slot(3);
+        state.current_slot = 2;
+        assert_eq!(state.duplicate_slot(3, 0), 1);
+        assert_eq!(state.slot().name, "Solution 3");
+        assert_eq!(state.duplicate_slot(3, 3), 4);
+        assert_eq!(state.slot().name, "Solution 3");
+        state.duplicate_slot(4, 0);
+        assert_eq!(state.current_slot, 3);
+    }
+
+    #[test]
+    fn practice_level_leads_to_the_first_open_level() {
+        let mut state = state();
+        assert_eq!(state.next_level(PRACTICE_LEVEL), Some(0));
+        state.record_completion(0, (10, 10, 1), String::new());
+        assert_eq!(state.next_level(PRACTICE_LEVEL), Some(1));
+        assert_eq!(state.next_level(0), Some(1));
+        state.record_completion(1, (10, 10, 1), String::new());
+        assert_eq!(state.next_level(1), Some(2));
+    }
+}
@@ -1,18 +1,26 @@
 use std::collections::VecDeque;
 
-use bevy::{ecs::schedule::ParallelExecutor, prelude::*, utils::HashSet};
+use bevy::{
+    ecs::schedule::ParallelExecutor,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 
 use crate::{
-    animations::Animation,
+    animations::{Animation, Easing},
     block::{Block, BlockFacing, BlockKind},
     interface::{exit_level, ChangeToCompleteRequest, InterfaceState},
+    metrics::Metrics,
     setup::LevelEntity,
     setup_menu::GlobalState,
-    structure::{Beam, Structure},
+    structure::{BeamState, Structure},
     world::{Part, Position, World, WorldSnapshot},
-    GameState, Sfx,
+    GameState,
 };
 
+/// How many outputs complete a level.
+pub const OUTPUTS_TO_COLLECT: usize = 10;
+
 pub struct SimulationState {
     pub started: bool,
     pub running: bool,
@@ -21,6 +29,39 @@ pub struct SimulationState {
     pub cycles: usize,
     pub collected_outputs: usize,
     pub speed: f32,
+    /// Parts that moved on the last tick, by their physical instance.
+    pub moving_parts: HashSet<Entity>,
+    /// Parts that were pulled by a tractor beam but could not move.
+    pub blocked_parts: HashSet<Entity>,
+    /// What every beam did on the last tick, by its block as it is after the
+    /// tick's moves. Moving a part respawns its beams, so this is not kept on
+    /// the beam entities themselves.
+    pub beams: HashMap<Block, BeamTick>,
+    /// Running total for `Metrics::beam_distance`.
+    pub beam_distance: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BeamTick {
+    pub state: BeamState,
+    /// How long the beam is drawn at the start and at the end of the tick.
+    pub length: (f32, f32),
+    pub easing: Easing,
+    /// When the beam last welded something, in seconds since startup.
+    pub welded_at: Option<f64>,
+}
+
+/// Things that happen during a tick that something else may want to react to,
+/// such as playing a sound.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SimulationEvent {
+    InputSpawned,
+    Welded,
+    OutputCollected,
+    LevelComplete,
+    PartStartedMoving,
+    PartStopped,
+    PushBlocked,
 }
 
 impl SimulationState {
@@ -61,6 +102,10 @@ pub fn begin_simulation(
     simulation_state.existing_parts = world.parts().len();
     simulation_state.collected_outputs = 0;
     simulation_state.cycles = 0;
+    simulation_state.moving_parts.clear();
+    simulation_state.blocked_parts.clear();
+    simulation_state.beams.clear();
+    simulation_state.beam_distance = 0.0;
 }
 
 pub fn end_simulation(
@@ -78,7 +123,11 @@ pub fn end_simulation(
     simulation_state.started = false;
 }
 
-fn any_other_part_contains_block_at(parts: &[Part], exclude: usize, at_position: Position) -> bool {
+pub(crate) fn any_other_part_contains_block_at(
+    parts: &[Part],
+    exclude: usize,
+    at_position: Position,
+) -> bool {
     let parts: Box<dyn Iterator<Item = &Part>> = if exclude < parts.len() {
         Box::new(parts[..exclude].iter().chain(parts[exclude + 1..].iter()))
     } else {
@@ -144,6 +193,8 @@ fn part_touches(parts: &[Part], part: usize, in_direction: BlockFacing) -> HashS
 struct PhysicsState {
     can_move: [bool; 6],
     farthest_tractor_beam: [(i32, usize); 6],
+    /// The tractor beam responsible for each entry of `farthest_tractor_beam`.
+    beam: [Option<Block>; 6],
 }
 
 #[derive(Clone, Debug, Component)]
@@ -182,11 +233,159 @@ pub fn make_output(
         .insert(LevelEntity);
 }
 
+/// A change `step` made to the list of parts, in the order it made them.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StepChange {
+    /// An input spawned a new part at the end of the list.
+    Spawned(Structure),
+    /// These parts were welded together, see `merge_parts`.
+    Welded(Vec<usize>),
+    /// This part matched an output and was removed.
+    Collected(usize),
+}
+
+/// What happened during one `step`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Step {
+    pub changes: Vec<StepChange>,
+    /// Every output had been collected, so nothing after spawning happened.
+    pub complete: bool,
+    pub welder_states: Vec<(Block, BeamState)>,
+    /// The moves, already made to the parts.
+    pub plan: MovePlan,
+}
+
+/// As `World::merge_parts`, the merged part keeps the physical instance of the
+/// first one and goes to the end.
+fn merge_parts(parts: &mut Vec<Part>, welded: &[usize]) {
+    let mut welded = welded.to_vec();
+    welded.sort();
+    let kept = welded.remove(0);
+    welded.reverse();
+    let removed: Vec<_> = welded
+        .into_iter()
+        .map(|index| parts.remove(index))
+        .collect();
+    let mut kept = parts.remove(kept);
+    kept.structure.blocks.extend(
+        removed
+            .into_iter()
+            .flat_map(|part| part.structure.blocks.into_iter()),
+    );
+    parts.push(kept);
+}
+
+/// One tick of the rules on a plain list of parts: inputs spawn, welder beams
+/// weld, finished outputs are collected and then everything moves. Both
+/// `run_simulation` and the solver go through this, the first one then does
+/// the same changes to the `World`.
+pub fn step(
+    parts: &mut Vec<Part>,
+    inputs: &[Structure],
+    outputs: &[Structure],
+    existing_parts: usize,
+    collected_outputs: usize,
+) -> Step {
+    let mut step = Step::default();
+    for input in inputs {
+        let should_spawn = !input
+            .blocks
+            .iter()
+            .any(|block| any_other_part_contains_block_at(parts, usize::MAX, block.position));
+        if should_spawn {
+            parts.push(Part {
+                structure: input.clone(),
+                // `run_simulation` spawns the real one.
+                physical_instance: Entity::from_raw(0),
+                is_hologram: false,
+            });
+            step.changes.push(StepChange::Spawned(input.clone()));
+        }
+    }
+
+    if collected_outputs == OUTPUTS_TO_COLLECT {
+        step.complete = true;
+        return step;
+    }
+
+    let welders: Vec<Block> = all_blocks(parts)
+        .filter(|(_, x)| x.kind == BlockKind::WelderBeamSource)
+        .map(|(_, x)| *x)
+        .collect();
+    for block in welders {
+        let bp = block.position;
+        let o = block.facing.offset();
+        let own_part = find_part_containing_block_at(parts, bp);
+        let mut welded = false;
+        let mut hit_own_part = false;
+        let mut intersects = HashSet::new();
+        let mut weld = |parts: &mut Vec<Part>, intersects: &HashSet<usize>| {
+            let mut intersects: Vec<_> = intersects.iter().copied().collect();
+            intersects.sort();
+            merge_parts(parts, &intersects);
+            step.changes.push(StepChange::Welded(intersects));
+        };
+        for distance in 1..100 {
+            let position = (
+                bp.0 + distance * o.0,
+                bp.1 + distance * o.1,
+                bp.2 + distance * o.2,
+            );
+            if let Some(part_index) = find_part_containing_block_at(parts, position) {
+                if part_index < existing_parts {
+                    if intersects.len() > 1 {
+                        weld(parts, &intersects);
+                        welded = true;
+                    }
+                    hit_own_part = Some(part_index) == own_part;
+                    break;
+                } else {
+                    intersects.insert(part_index);
+                }
+            } else {
+                if intersects.len() > 1 {
+                    weld(parts, &intersects);
+                    welded = true;
+                }
+                intersects.clear();
+            }
+        }
+        let beam_state = if welded {
+            BeamState::Welding
+        } else if hit_own_part {
+            BeamState::Blocked
+        } else {
+            BeamState::Idle
+        };
+        step.welder_states.push((block, beam_state));
+    }
+
+    for output in outputs {
+        let matching_part_index = parts
+            .iter()
+            .position(|part| part.structure.matches(output) && !part.is_hologram);
+        if let Some(matching_part_index) = matching_part_index {
+            parts.remove(matching_part_index);
+            step.changes
+                .push(StepChange::Collected(matching_part_index));
+        }
+    }
+
+    step.plan = plan_moves(parts);
+    for planned in &step.plan.moves {
+        for &part_index in &planned.moved_parts {
+            parts[part_index]
+                .structure
+                .translate(planned.direction.offset());
+        }
+    }
+    step
+}
+
 fn run_simulation(
     mut commands: Commands,
     inputs: Query<&Input>,
     outputs: Query<&Output>,
-    mut beams: Query<(&mut Transform, &Beam)>,
     mut world: ResMut<World>,
     world_snapshot: Res<WorldSnapshot>,
     mut state: ResMut<SimulationState>,
@@ -194,8 +393,7 @@ fn run_simulation(
     assets: Res<AssetServer>,
     mut global_state: ResMut<GlobalState>,
     mut interface_state: ResMut<InterfaceState>,
-    sfx: Res<Sfx>,
-    audio: Res<Audio>,
+    mut events: EventWriter<SimulationEvent>,
 ) {
     if !state.running {
         return;
@@ -209,33 +407,58 @@ fn run_simulation(
         return;
     }
 
-    for input in inputs.iter() {
-        let mut blocks = input.spawns.blocks.iter();
-        let parts = world.parts();
-        let should_spawn = !blocks
-            .any(|block| any_other_part_contains_block_at(parts, usize::MAX, block.position));
-        if should_spawn {
-            world.add_part(input.spawns.clone(), &mut commands, &*assets);
+    let inputs: Vec<_> = inputs.iter().map(|input| input.spawns.clone()).collect();
+    let outputs: Vec<_> = outputs
+        .iter()
+        .map(|output| output.accepts.clone())
+        .collect();
+    let mut parts = world.parts().to_vec();
+    let step = step(
+        &mut parts,
+        &inputs,
+        &outputs,
+        state.existing_parts,
+        state.collected_outputs,
+    );
+
+    // The same changes again, this time with something to see.
+    for change in step.changes {
+        match change {
+            StepChange::Spawned(structure) => {
+                world.add_part(structure, &mut commands, &*assets);
+                events.send(SimulationEvent::InputSpawned);
+            }
+            StepChange::Welded(welded) => {
+                world.merge_parts(welded.into_iter(), &mut commands, &*assets);
+                events.send(SimulationEvent::Welded);
+            }
+            StepChange::Collected(index) => {
+                world.remove_part(index, &mut commands);
+                state.collected_outputs += 1;
+                if state.collected_outputs < OUTPUTS_TO_COLLECT {
+                    events.send(SimulationEvent::OutputCollected);
+                }
+            }
         }
     }
 
-    if state.collected_outputs == 10 {
+    if step.complete {
         let level = global_state.current_level;
+        let user_parts = &world.parts()[interface_state.first_user_part..state.existing_parts];
         let mut num_blocks = 0;
-        for part in &world.parts()[interface_state.first_user_part..state.existing_parts] {
+        for part in user_parts {
             num_blocks += part.structure.blocks.len();
         }
-        global_state.last[level] = Some((
+        let score = (
             state.cycles as u32 - 1,
             num_blocks as u32,
             (state.existing_parts - interface_state.first_user_part) as u32,
-        ));
-        let (gs_cycles, gs_num_blocks, gs_parts) =
-            global_state.completed[level].get_or_insert((u32::MAX, u32::MAX, u32::MAX));
-        *gs_cycles = (*gs_cycles).min(state.cycles as u32 - 1);
-        *gs_num_blocks = (*gs_num_blocks).min(num_blocks as u32);
-        *gs_parts =
-            (*gs_parts).min((state.existing_parts - interface_state.first_user_part) as u32);
+        );
+        // Measured on the machine as it was built, the run has moved it about.
+        let built_parts = &world_snapshot.0.parts()[interface_state.first_user_part..];
+        let metrics = Metrics::measure(built_parts, state.beam_distance);
+        global_state.last[level] = Some(score);
+        global_state.last_metrics[level] = Some(metrics);
         exit_level(
             &mut commands,
             &world_snapshot.0,
@@ -243,70 +466,143 @@ fn run_simulation(
             &mut *global_state,
             true,
         );
+        global_state.slot_mut().record(score);
+        // `exit_level` has just saved the machine into the slot.
+        let solution = global_state.slot().level.clone();
+        global_state.record_completion(level, score, solution);
         commands.insert_resource(ChangeToCompleteRequest);
-        audio.play_with_settings(
-            sfx.level_complete.clone(),
-            PlaybackSettings::ONCE.with_volume(0.3),
-        );
+        events.send(SimulationEvent::LevelComplete);
+        return;
     }
 
-    let parts: Vec<_> = world.parts().iter().cloned().collect();
-    for (_, block) in all_blocks(&parts).filter(|(_, x)| x.kind == BlockKind::WelderBeamSource) {
-        let bp = block.position;
-        let o = block.facing.offset();
-        let (mut transform, _) = beams.iter_mut().find(|x| &x.1.for_block == block).unwrap();
-        transform.scale = Vec3::ZERO;
-        let mut intersects = HashSet::new();
-        for distance in 1..100 {
-            let parts = world.parts();
-            let position = (
-                bp.0 + distance * o.0,
-                bp.1 + distance * o.1,
-                bp.2 + distance * o.2,
+    let plan = step.plan;
+    let mut beam_states: HashMap<_, _> = step.welder_states.into_iter().collect();
+    // The parts as they were before the moves, which `step` has already made.
+    beam_states.extend(tractor_beam_states(world.parts(), &plan));
+
+    let before = world.parts().to_vec();
+    // Summed up since a part can be pushed along and then move on its own.
+    let mut offsets: HashMap<usize, Vec3> = HashMap::new();
+    for planned in &plan.moves {
+        let o = planned.direction.offset();
+        for &part_index in &planned.moved_parts {
+            *offsets.entry(part_index).or_default() += Vec3::new(o.0 as _, o.1 as _, o.2 as _);
+            world.modify_part(
+                part_index,
+                |part| part.translate(o),
+                &mut commands,
+                &*assets,
             );
-            if let Some(part_index) = find_part_containing_block_at(parts, position) {
-                transform.scale = Vec3::new(distance as f32 - 0.5, 1.0, 1.0);
-                if part_index < state.existing_parts {
-                    if intersects.len() > 1 {
-                        world.merge_parts(intersects.iter().copied(), &mut commands, &*assets);
-                    }
-                    break;
-                } else {
-                    intersects.insert(part_index);
-                }
-            } else {
-                if intersects.len() > 1 {
-                    world.merge_parts(intersects.iter().copied(), &mut commands, &*assets);
-                }
-                intersects.clear();
-            }
         }
     }
 
-    for output in outputs.iter() {
-        let parts = world.parts();
-        let matching_part_index = parts
-            .iter()
-            .position(|part| part.structure.matches(&output.accepts) && !part.is_hologram);
-        if let Some(matching_part_index) = matching_part_index {
-            world.remove_part(matching_part_index, &mut commands);
-            state.collected_outputs += 1;
-            if state.collected_outputs < 10 {
-                audio.play_with_settings(sfx.ding.clone(), PlaybackSettings::ONCE.with_volume(0.5));
+    // Looking one tick ahead tells which parts are about to come to rest.
+    let moving_next: HashSet<usize> = plan_moves(world.parts())
+        .moves
+        .iter()
+        .flat_map(|planned| planned.moved_parts.iter().copied())
+        .collect();
+    let mut moved = HashSet::new();
+    let mut easings = HashMap::new();
+    for (&part_index, &offset) in &offsets {
+        let instance = world.parts()[part_index].physical_instance;
+        moved.insert(instance);
+        let easing = Easing::for_move(
+            !state.moving_parts.contains(&instance),
+            !moving_next.contains(&part_index),
+        );
+        easings.insert(part_index, easing);
+        world.animate_part(
+            part_index,
+            Animation::Lerp(-offset, Vec3::ZERO, easing),
+            &mut commands,
+        );
+    }
+    // Only parts that moved last tick still have a move animation on them.
+    for part_index in 1..world.parts().len() {
+        let instance = world.parts()[part_index].physical_instance;
+        if !offsets.contains_key(&part_index) && state.moving_parts.contains(&instance) {
+            world.animate_part(part_index, Animation::Stationary, &mut commands);
+        }
+    }
+
+    let now = time.seconds_since_startup();
+    let mut beams = HashMap::new();
+    for (part_index, part) in world.parts().iter().enumerate() {
+        for (block_index, block) in part.structure.blocks.iter().enumerate() {
+            if block.kind != BlockKind::TractorBeamSource
+                && block.kind != BlockKind::WelderBeamSource
+            {
+                continue;
+            }
+            // Moves keep the order of parts and blocks.
+            let old_block = &before[part_index].structure.blocks[block_index];
+            let beam_state = beam_states
+                .get(old_block)
+                .copied()
+                .unwrap_or(BeamState::Idle);
+            // The length follows whatever is moving at the end of the beam.
+            let eased_part = plan
+                .moves
+                .iter()
+                .find(|planned| planned.pulled_by == Some(*old_block))
+                .map_or(part_index, |planned| planned.part);
+            let welded_at = if beam_state == BeamState::Welding {
+                Some(now)
+            } else {
+                state.beams.get(old_block).and_then(|x| x.welded_at)
+            };
+            let tick = BeamTick {
+                state: beam_state,
+                length: (
+                    beam_length(&before, old_block, state.existing_parts),
+                    beam_length(world.parts(), block, state.existing_parts),
+                ),
+                easing: easings.get(&eased_part).copied().unwrap_or(Easing::Linear),
+                welded_at,
+            };
+            if matches!(beam_state, BeamState::Pulling | BeamState::Welding) {
+                state.beam_distance += tick.length.1;
             }
+            beams.insert(*block, tick);
         }
     }
+    state.beams = beams;
+    let blocked: HashSet<_> = plan
+        .blocked
+        .iter()
+        .map(|&index| world.parts()[index].physical_instance)
+        .collect();
+
+    if moved.difference(&state.moving_parts).next().is_some() {
+        events.send(SimulationEvent::PartStartedMoving);
+    }
+    let still_exist: HashSet<_> = world.parts().iter().map(|x| x.physical_instance).collect();
+    if state
+        .moving_parts
+        .difference(&moved)
+        .any(|x| still_exist.contains(x))
+    {
+        events.send(SimulationEvent::PartStopped);
+    }
+    if blocked.difference(&state.blocked_parts).next().is_some() {
+        events.send(SimulationEvent::PushBlocked);
+    }
+    state.moving_parts = moved;
+    state.blocked_parts = blocked;
+}
 
-    let parts = world.parts();
+/// Works out which tractor beam wins in each direction for every part.
+fn tractor_pulls(parts: &[Part]) -> Vec<PhysicsState> {
     let mut states = vec![
         PhysicsState {
             can_move: [false; 6],
-            farthest_tractor_beam: [(0, 0); 6]
+            farthest_tractor_beam: [(0, 0); 6],
+            beam: [None; 6],
         };
         parts.len()
     ];
     let directions = BlockFacing::all();
-
     for (part_containing_tractor_beam, block) in
         all_blocks(parts).filter(|(_, x)| x.kind == BlockKind::TractorBeamSource)
     {
@@ -317,8 +613,6 @@ fn run_simulation(
             .unwrap();
         let bp = block.position;
         let o = block.facing.offset();
-        let (mut transform, _) = beams.iter_mut().find(|x| &x.1.for_block == block).unwrap();
-        transform.scale = Vec3::ZERO;
         for distance in 1..100 {
             let position = (
                 bp.0 + distance * o.0,
@@ -326,67 +620,149 @@ fn run_simulation(
                 bp.2 + distance * o.2,
             );
             if let Some(part_index) = find_part_containing_block_at(parts, position) {
-                transform.scale = Vec3::new(distance as f32 - 0.5, 1.0, 1.0);
                 if part_index == part_containing_tractor_beam {
                     break;
                 }
-                let ftb = &mut states[part_index].farthest_tractor_beam[pull_direction_index];
+                let state = &mut states[part_index];
+                let ftb = &mut state.farthest_tractor_beam[pull_direction_index];
                 ftb.0 = ftb.0.max(distance);
                 if ftb.0 == distance {
                     ftb.1 = part_containing_tractor_beam;
+                    state.beam[pull_direction_index] = Some(*block);
                 }
                 break;
             }
         }
     }
+    states
+}
 
-    for part_index in 1..parts.len() {
-        world.animate_part(part_index, Animation::Stationary, &mut commands);
+/// What each tractor beam is doing given the moves planned for this tick.
+fn tractor_beam_states(parts: &[Part], plan: &MovePlan) -> Vec<(Block, BeamState)> {
+    all_blocks(parts)
+        .filter(|(_, x)| x.kind == BlockKind::TractorBeamSource)
+        .map(|(own_part, block)| {
+            let bp = block.position;
+            let o = block.facing.offset();
+            let hit = (1..100).find_map(|distance| {
+                let position = (
+                    bp.0 + distance * o.0,
+                    bp.1 + distance * o.1,
+                    bp.2 + distance * o.2,
+                );
+                find_part_containing_block_at(parts, position)
+            });
+            let pulling = plan
+                .moves
+                .iter()
+                .any(|planned| planned.pulled_by == Some(*block));
+            let state = match hit {
+                None => BeamState::Idle,
+                Some(part) if part == own_part => BeamState::Blocked,
+                Some(_) if pulling => BeamState::Pulling,
+                Some(part) if plan.blocked.contains(&part) => BeamState::Blocked,
+                Some(_) => BeamState::Idle,
+            };
+            (*block, state)
+        })
+        .collect()
+}
+
+/// How long a beam is drawn. Tractor beams stop at the first thing they hit,
+/// welder beams go through spawned parts until they reach one of the parts
+/// that existed when the simulation started.
+fn beam_length(parts: &[Part], block: &Block, existing_parts: usize) -> f32 {
+    let bp = block.position;
+    let o = block.facing.offset();
+    let mut length = 0.0;
+    for distance in 1..100 {
+        let position = (
+            bp.0 + distance * o.0,
+            bp.1 + distance * o.1,
+            bp.2 + distance * o.2,
+        );
+        if let Some(part_index) = find_part_containing_block_at(parts, position) {
+            length = distance as f32 - 0.5;
+            if block.kind == BlockKind::TractorBeamSource || part_index < existing_parts {
+                break;
+            }
+        }
     }
+    length
+}
 
-    let parts = world.parts();
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlannedMove {
+    /// The part being pulled. Every part in `moved_parts` is pushed along.
+    pub part: usize,
+    pub direction: BlockFacing,
+    pub moved_parts: Vec<usize>,
+    /// The tractor beam doing the pulling, `None` when the part is falling.
+    pub pulled_by: Option<Block>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct MovePlan {
+    /// In the order they happen, later moves see the result of earlier ones.
+    pub moves: Vec<PlannedMove>,
+    /// Parts pulled sideways or upwards by a tractor beam that cannot move.
+    pub blocked: Vec<usize>,
+}
+
+/// Decides how every part moves during the movement step of a tick, without
+/// touching the world. Parts are handled in order and a part that moves pushes
+/// everything in front of it, so each decision sees the moves made before it.
+pub fn plan_moves(parts: &[Part]) -> MovePlan {
+    let mut states = tractor_pulls(parts);
+    let mut parts = parts.to_vec();
+    let mut plan = MovePlan::default();
+    let directions = BlockFacing::all();
     for part_index in 1..parts.len() {
-        if world.parts()[part_index].is_hologram {
+        if parts[part_index].is_hologram {
             continue;
         }
         let state = &mut states[part_index];
+        // Gravity is not a push, resting on the floor is not worth a sound.
+        let pulled = (0..6).any(|index| index != 1 && state.farthest_tractor_beam[index].0 > 1);
         // Gravity.
         let upwards_pull = state.farthest_tractor_beam[0].0;
-        state.farthest_tractor_beam[1].0 = if upwards_pull < 1 {
-            i32::MAX
-        } else {
-            upwards_pull
-        };
+        let falling = upwards_pull < 1;
+        state.farthest_tractor_beam[1].0 = if falling { i32::MAX } else { upwards_pull };
         let mut directions: Vec<_> = directions.iter().copied().enumerate().collect();
         directions.sort_by_key(|&(idx, _)| -state.farthest_tractor_beam[idx].0);
+        let mut did_move = false;
         for (direction_index, direction) in directions {
-            let parts = world.parts();
-            let touches = part_touches(parts, part_index, direction);
+            let touches = part_touches(&parts, part_index, direction);
             let can_move = !touches.contains(&state.farthest_tractor_beam[direction_index].1)
                 && !touches.contains(&0);
             if can_move && state.farthest_tractor_beam[direction_index].0 > 1 {
-                for part_index in touches.into_iter() {
-                    let o = direction.offset();
-                    let start = Vec3::new(-o.0 as _, -o.1 as _, -o.2 as _);
-                    world.animate_part(
-                        part_index,
-                        Animation::Lerp(start, Vec3::ZERO),
-                        &mut commands,
-                    );
-                    world.modify_part(
-                        part_index,
-                        |part| part.translate(o),
-                        &mut commands,
-                        &*assets,
-                    );
+                let mut moved_parts: Vec<_> = touches.into_iter().collect();
+                moved_parts.sort();
+                for &moved in &moved_parts {
+                    parts[moved].structure.translate(direction.offset());
                 }
+                plan.moves.push(PlannedMove {
+                    part: part_index,
+                    direction,
+                    moved_parts,
+                    pulled_by: if direction_index == 1 && falling {
+                        None
+                    } else {
+                        state.beam[direction_index]
+                    },
+                });
+                did_move = true;
                 break;
             }
         }
+        if pulled && !did_move {
+            plan.blocked.push(part_index);
+        }
     }
+    plan
 }
 
-fn find_part_containing_block_at(parts: &[Part], position: Position) -> Option<usize> {
+pub(crate) fn find_part_containing_block_at(parts: &[Part], position: Position) -> Option<usize> {
     parts.iter().position(|part| {
         !part.is_hologram
             && part
@@ -397,7 +773,7 @@ fn find_part_containing_block_at(parts: &[Part], position: Position) -> Option<u
     })
 }
 
-fn all_blocks(parts: &[Part]) -> impl Iterator<Item = (usize, &Block)> {
+pub(crate) fn all_blocks(parts: &[Part]) -> impl Iterator<Item = (usize, &Block)> {
     parts
         .iter()
         .enumerate()
@@ -413,6 +789,7 @@ impl Plugin for SimulationPlugin {
             "asdf",
             SystemStage::new(Box::new(ParallelExecutor::default())),
         );
+        app.add_event::<SimulationEvent>();
         app.add_system_set_to_stage("asdf", State::<GameState>::get_driver());
         app.add_system_set_to_stage(
             "asdf",
@@ -0,0 +1,413 @@
+//! Shown when a level is picked on the menu, to choose which of its solutions
+//! to play and to add, copy, rename or delete them, or to bring back best
+//! solutions that no slot holds any more.
+
+use bevy::{
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+    window::ReceivedCharacter,
+};
+
+use crate::{
+    audio_settings::AudioSettings,
+    controls::{Action, ActionEvent},
+    gamepad::VirtualCursor,
+    setup_complete::absolute,
+    setup_menu::{
+        ChangeToLevelRequest, GlobalState, MenuEntity, MenuRoot, MAX_SLOTS, PRACTICE_LEVEL,
+    },
+    GameState, Sfx,
+};
+
+/// Inserted while the picker is open.
+pub struct SlotPicker {
+    level: usize,
+    selected: usize,
+    /// The new name while the selected slot is being renamed.
+    renaming: Option<String>,
+    /// The click that opened the picker should not also press a button in it.
+    just_opened: bool,
+    panel: Option<Entity>,
+}
+
+impl SlotPicker {
+    pub fn open(level: usize, global_state: &GlobalState) -> Self {
+        let selected = if level == global_state.current_level {
+            global_state.current_slot
+        } else {
+            0
+        };
+        Self {
+            level,
+            selected,
+            renaming: None,
+            just_opened: true,
+            panel: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PickerButton {
+    Play,
+    New,
+    Duplicate,
+    Rename,
+    Delete,
+    Restore,
+    Cancel,
+}
+
+const BUTTONS: [PickerButton; 7] = [
+    PickerButton::Play,
+    PickerButton::New,
+    PickerButton::Duplicate,
+    PickerButton::Rename,
+    PickerButton::Delete,
+    PickerButton::Restore,
+    PickerButton::Cancel,
+];
+
+impl PickerButton {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Play => "Play",
+            Self::New => "New",
+            Self::Duplicate => "Copy",
+            Self::Rename => "Rename",
+            Self::Delete => "Delete",
+            Self::Restore => "Best",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    fn enabled(&self, global_state: &GlobalState, level: usize) -> bool {
+        let slots = global_state.slots[level].len();
+        match self {
+            Self::New | Self::Duplicate => slots < MAX_SLOTS,
+            Self::Delete => slots > 1,
+            Self::Restore => global_state.can_restore_front(level),
+            _ => true,
+        }
+    }
+}
+
+/// Corners of a slot's row, in percent of the window from the bottom left.
+fn row_rect(index: usize) -> (Vec2, Vec2) {
+    let top = 75.0 - 6.5 * index as f32;
+    (Vec2::new(27.0, top - 5.5), Vec2::new(73.0, top))
+}
+
+fn button_rect(index: usize) -> (Vec2, Vec2) {
+    let left = 27.0 + 6.6 * index as f32;
+    (Vec2::new(left, 17.0), Vec2::new(left + 6.0, 24.0))
+}
+
+pub fn contains((min, max): (Vec2, Vec2), point: Vec2) -> bool {
+    point.cmpge(min).all() && point.cmple(max).all()
+}
+
+const ROW_COLOR: &str = "264653";
+const SELECTED_COLOR: &str = "2A9D8F";
+const BUTTON_COLOR: &str = "2A9D8F";
+const BUTTON_HOVER_COLOR: &str = "E9C46A";
+
+pub fn spawn_box(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    (min, max): (Vec2, Vec2),
+    color: Color,
+    label: String,
+    centered: bool,
+) -> Entity {
+    let size = max - min;
+    let justify_content = if centered {
+        JustifyContent::Center
+    } else {
+        JustifyContent::FlexStart
+    };
+    commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                justify_content,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(6.0)),
+                ..absolute(min.x, min.y, size.x, size.y)
+            },
+            color: UiColor(color),
+            ..Default::default()
+        })
+        .with_children(|node| {
+            node.spawn_bundle(TextBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font: assets.load("RobotoSlab-Regular.ttf"),
+                        font_size: 22.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                ..Default::default()
+            });
+        })
+        .id()
+}
+
+fn spawn_panel(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    picker: &SlotPicker,
+    global_state: &GlobalState,
+    hovered_button: Option<usize>,
+) -> Entity {
+    let panel = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: absolute(25.0, 15.0, 50.0, 70.0),
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.85)),
+            ..Default::default()
+        })
+        .insert(MenuEntity)
+        .id();
+    let title = match picker.renaming {
+        Some(_) => "Type a name, enter to keep it".to_owned(),
+        None if picker.level == PRACTICE_LEVEL => {
+            format!("Practice seed {} solutions", global_state.practice_seed)
+        }
+        None => format!("Level {} solutions", picker.level + 1),
+    };
+    // Children are positioned relative to the panel, the rects are not.
+    let relative = |(min, max): (Vec2, Vec2)| {
+        let to_panel = |point: Vec2| (point - Vec2::new(25.0, 15.0)) / Vec2::new(0.5, 0.7);
+        (to_panel(min), to_panel(max))
+    };
+    let title = spawn_box(
+        commands,
+        assets,
+        relative((Vec2::new(27.0, 77.0), Vec2::new(73.0, 84.0))),
+        Color::NONE,
+        title,
+        false,
+    );
+    commands.entity(panel).add_child(title);
+    let slots = &global_state.slots[picker.level];
+    for (index, slot) in slots.iter().enumerate() {
+        let selected = index == picker.selected;
+        let name = match &picker.renaming {
+            Some(name) if selected => format!("{}_", name),
+            _ => slot.name.clone(),
+        };
+        let score = match slot.best {
+            Some((cycles, blocks, parts)) => format!("{}c  {}b  {}p", cycles, blocks, parts),
+            None => "not completed".to_owned(),
+        };
+        let color = Color::hex(if selected { SELECTED_COLOR } else { ROW_COLOR }).unwrap();
+        let label = format!("{}    {}", name, score);
+        let row = spawn_box(
+            commands,
+            assets,
+            relative(row_rect(index)),
+            color,
+            label,
+            false,
+        );
+        commands.entity(panel).add_child(row);
+    }
+    for (index, button) in BUTTONS.into_iter().enumerate() {
+        let color = if !button.enabled(global_state, picker.level) || picker.renaming.is_some() {
+            Color::rgba(1.0, 1.0, 1.0, 0.15)
+        } else if hovered_button == Some(index) {
+            Color::hex(BUTTON_HOVER_COLOR).unwrap()
+        } else {
+            Color::hex(BUTTON_COLOR).unwrap()
+        };
+        let label = button.label().to_owned();
+        let button = spawn_box(
+            commands,
+            assets,
+            relative(button_rect(index)),
+            color,
+            label,
+            true,
+        );
+        commands.entity(panel).add_child(button);
+    }
+    panel
+}
+
+/// What a frame of input amounts to.
+enum PickerInput {
+    Button(PickerButton),
+    Select(usize),
+    ConfirmRename,
+    CancelRename,
+}
+
+fn slot_picker_system(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    picker: Option<ResMut<SlotPicker>>,
+    mut global_state: ResMut<GlobalState>,
+    roots: Query<Entity, With<MenuRoot>>,
+    windows: Res<Windows>,
+    virtual_cursor: Res<VirtualCursor>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut action_events: EventReader<ActionEvent>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut hovered: Local<Option<usize>>,
+    sfx: Res<Sfx>,
+    audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
+) {
+    // Read even while closed so that old events do not pile up for later.
+    let clicking = mouse_events
+        .iter()
+        .any(|e| e.button == MouseButton::Left && e.state == ButtonState::Pressed);
+    let actions: Vec<_> = action_events
+        .iter()
+        .filter(|e| e.pressed)
+        .map(|e| e.action)
+        .collect();
+    let keys: Vec<_> = keyboard_events
+        .iter()
+        .filter(|e| e.state == ButtonState::Pressed)
+        .filter_map(|e| e.key_code)
+        .collect();
+    let typed: String = characters
+        .iter()
+        .map(|e| e.char)
+        .filter(|c| !c.is_control())
+        .collect();
+    let mut picker = match picker {
+        Some(picker) => picker,
+        None => return,
+    };
+    let clicking = clicking || actions.contains(&Action::Click);
+    if picker.just_opened {
+        picker.just_opened = false;
+        let root = roots.single();
+        let panel = spawn_panel(&mut commands, &assets, &picker, &global_state, None);
+        commands.entity(root).add_child(panel);
+        picker.panel = Some(panel);
+        return;
+    }
+
+    let win = windows.get_primary().unwrap();
+    let cursor = virtual_cursor
+        .cursor_position(win)
+        .map(|x| x * 100.0 / Vec2::new(win.width(), win.height()));
+    let slots = global_state.slots[picker.level].len();
+    let hovered_button = cursor
+        .and_then(|cursor| (0..BUTTONS.len()).find(|&index| contains(button_rect(index), cursor)));
+    let mut input = None;
+    if let Some(name) = &mut picker.renaming {
+        name.push_str(&typed);
+        if keys.contains(&KeyCode::Back) {
+            name.pop();
+        }
+        if actions.contains(&Action::ConfirmStart) {
+            input = Some(PickerInput::ConfirmRename);
+        } else if actions.contains(&Action::Back) {
+            input = Some(PickerInput::CancelRename);
+        }
+    } else {
+        if clicking {
+            let row = cursor
+                .and_then(|cursor| (0..slots).find(|&index| contains(row_rect(index), cursor)));
+            input = match (row, hovered_button) {
+                (Some(row), _) => Some(PickerInput::Select(row)),
+                (None, Some(index)) => Some(PickerInput::Button(BUTTONS[index])),
+                (None, None) => None,
+            };
+        }
+        if keys.contains(&KeyCode::Up) {
+            input = Some(PickerInput::Select((picker.selected + slots - 1) % slots));
+        } else if keys.contains(&KeyCode::Down) {
+            input = Some(PickerInput::Select((picker.selected + 1) % slots));
+        }
+        if actions.contains(&Action::ConfirmStart) {
+            input = Some(PickerInput::Button(PickerButton::Play));
+        } else if actions.contains(&Action::Back) {
+            input = Some(PickerInput::Button(PickerButton::Cancel));
+        }
+    }
+
+    let level = picker.level;
+    let mut changed = input.is_some() || *hovered != hovered_button;
+    match input {
+        Some(PickerInput::Button(button)) if !button.enabled(&global_state, level) => (),
+        Some(PickerInput::Button(button)) => {
+            audio_settings.play(&*audio, &sfx.click, 0.3);
+            match button {
+                PickerButton::Play => {
+                    global_state.current_level = level;
+                    global_state.current_slot = picker.selected;
+                    commands.insert_resource(ChangeToLevelRequest);
+                }
+                PickerButton::New => picker.selected = global_state.new_slot(level),
+                PickerButton::Duplicate => {
+                    picker.selected = global_state.duplicate_slot(level, picker.selected)
+                }
+                PickerButton::Rename => {
+                    picker.renaming = Some(global_state.slots[level][picker.selected].name.clone())
+                }
+                PickerButton::Delete => {
+                    global_state.delete_slot(level, picker.selected);
+                    picker.selected = picker.selected.min(slots - 2);
+                }
+                PickerButton::Restore => {
+                    if let Some(first) = global_state.restore_front(level) {
+                        picker.selected = first;
+                    }
+                }
+                PickerButton::Cancel => {
+                    if let Some(panel) = picker.panel {
+                        commands.entity(panel).despawn_recursive();
+                    }
+                    commands.remove_resource::<SlotPicker>();
+                    return;
+                }
+            }
+        }
+        Some(PickerInput::Select(index)) => picker.selected = index,
+        Some(PickerInput::ConfirmRename) => {
+            let name = picker.renaming.take().unwrap();
+            if !name.trim().is_empty() {
+                global_state.slots[level][picker.selected].name = name.trim().to_owned();
+            }
+        }
+        Some(PickerInput::CancelRename) => picker.renaming = None,
+        None => (),
+    }
+
+    // Rebuilt whenever something it shows changes, it only has a few nodes.
+    if picker.renaming.is_some() {
+        changed |= !typed.is_empty() || keys.contains(&KeyCode::Back);
+    }
+    if changed {
+        *hovered = hovered_button;
+        if let Some(panel) = picker.panel {
+            commands.entity(panel).despawn_recursive();
+        }
+        let root = roots.single();
+        let panel = spawn_panel(
+            &mut commands,
+            &assets,
+            &picker,
+            &global_state,
+            hovered_button,
+        );
+        commands.entity(root).add_child(panel);
+        picker.panel = Some(panel);
+    }
+}
+
+pub struct SlotPickerPlugin;
+
+impl Plugin for SlotPickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Menu).with_system(slot_picker_system));
+    }
+}
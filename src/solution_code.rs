@@ -0,0 +1,308 @@
+//! Short codes for the user parts of a solution, to paste into chat. The parts
+//! are listed block by block, each position stored as the difference from the
+//! previous one so that neighbouring blocks take a byte per axis. That and the
+//! level number are deflated, which makes the rows and walls most machines are
+//! built from much shorter, then base64 encoded after a version and with a
+//! checksum.
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+
+use crate::{
+    block::{Block, BlockFacing, BlockKind},
+    structure::Structure,
+    world::Position,
+};
+
+const PREFIX: &str = "bm:";
+const VERSION: u8 = 2;
+/// Far more than any level has room for, so that a made up code cannot have
+/// the game inflate it to gigabytes.
+const MAX_INFLATED: usize = 1 << 20;
+
+/// The kinds the player can place, anything else cannot be in a code.
+const KINDS: [BlockKind; 3] = [
+    BlockKind::Structure,
+    BlockKind::TractorBeamSource,
+    BlockKind::WelderBeamSource,
+];
+const FACINGS: [BlockFacing; 6] = [
+    BlockFacing::Px,
+    BlockFacing::Py,
+    BlockFacing::Nx,
+    BlockFacing::Ny,
+    BlockFacing::Pz,
+    BlockFacing::Nz,
+];
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| {
+            bits | (byte as u32) << (16 - 8 * index)
+        });
+        // Two characters for one byte, three for two and four for three.
+        for index in 0..=chunk.len() {
+            output.push(BASE64[(bits >> (18 - 6 * index) & 63) as usize] as char);
+        }
+    }
+    output
+}
+
+fn from_base64(text: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.bytes() {
+        let value = BASE64.iter().position(|&x| x == c)? as u32;
+        bits = bits << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            output.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Some(output)
+}
+
+/// 32 bit FNV-1a, only there to catch codes that got cut off or mistyped.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+fn push_number(bytes: &mut Vec<u8>, number: i32) {
+    // Zigzag, so that small negative numbers stay small.
+    let mut number = ((number << 1) ^ (number >> 31)) as u32;
+    while number >= 0x80 {
+        bytes.push(number as u8 | 0x80);
+        number >>= 7;
+    }
+    bytes.push(number as u8);
+}
+
+fn read_number(bytes: &mut impl Iterator<Item = u8>) -> Option<i32> {
+    let mut number = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        number |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Some((number >> 1) as i32 ^ -((number & 1) as i32));
+        }
+    }
+    None
+}
+
+pub fn encode(level: usize, parts: &[Structure]) -> String {
+    let mut bytes = vec![level as u8];
+    push_number(&mut bytes, parts.len() as i32);
+    let mut previous = (0, 0, 0);
+    for part in parts {
+        push_number(&mut bytes, part.blocks.len() as i32);
+        for block in &part.blocks {
+            let kind = KINDS.iter().position(|&x| x == block.kind).unwrap_or(0);
+            let facing = FACINGS.iter().position(|&x| x == block.facing).unwrap();
+            bytes.push((kind * FACINGS.len() + facing) as u8);
+            let (x, y, z) = block.position;
+            push_number(&mut bytes, x - previous.0);
+            push_number(&mut bytes, y - previous.1);
+            push_number(&mut bytes, z - previous.2);
+            previous = block.position;
+        }
+    }
+    let mut bytes = [&[VERSION][..], &compress_to_vec(&bytes, 10)].concat();
+    bytes.extend(checksum(&bytes).to_le_bytes());
+    format!("{}{}", PREFIX, to_base64(&bytes))
+}
+
+/// The level the code was made for and its parts.
+pub fn decode(code: &str) -> Result<(usize, Vec<Structure>), &'static str> {
+    let code = code
+        .trim()
+        .strip_prefix(PREFIX)
+        .ok_or("not a solution code")?;
+    let bytes = from_base64(code).ok_or("not a solution code")?;
+    if bytes.len() < 6 {
+        return Err("the code is too short");
+    }
+    let damaged = "the code is damaged";
+    let (bytes, sum) = bytes.split_at(bytes.len() - 4);
+    if checksum(bytes).to_le_bytes() != sum {
+        return Err(damaged);
+    }
+    if bytes[0] != VERSION {
+        return Err("the code is from a different version of the game");
+    }
+    let bytes = decompress_to_vec_with_limit(&bytes[1..], MAX_INFLATED).map_err(|_| damaged)?;
+    let level = *bytes.first().ok_or(damaged)? as usize;
+    let mut bytes = bytes[1..].iter().copied();
+    let part_count = read_number(&mut bytes).ok_or(damaged)?;
+    let mut parts = Vec::new();
+    let mut previous: Position = (0, 0, 0);
+    for _ in 0..part_count {
+        let block_count = read_number(&mut bytes).ok_or(damaged)?;
+        let mut part = Structure { blocks: Vec::new() };
+        for _ in 0..block_count {
+            let kind = bytes.next().ok_or(damaged)? as usize;
+            let dx = read_number(&mut bytes).ok_or(damaged)?;
+            let dy = read_number(&mut bytes).ok_or(damaged)?;
+            let dz = read_number(&mut bytes).ok_or(damaged)?;
+            let position = (
+                previous.0.checked_add(dx).ok_or(damaged)?,
+                previous.1.checked_add(dy).ok_or(damaged)?,
+                previous.2.checked_add(dz).ok_or(damaged)?,
+            );
+            part.blocks.push(Block {
+                kind: *KINDS.get(kind / FACINGS.len()).ok_or(damaged)?,
+                facing: FACINGS[kind % FACINGS.len()],
+                position,
+            });
+            previous = position;
+        }
+        parts.push(part);
+    }
+    Ok((level, parts))
+}
+
+/// Fails with a message to show the player.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut x| x.set_text(text.to_owned()))
+        .map_err(|error| format!("Could not copy to the clipboard: {}", error))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn paste_from_clipboard() -> Result<String, String> {
+    arboard::Clipboard::new()
+        .and_then(|mut x| x.get_text())
+        .map_err(|error| format!("Could not paste from the clipboard: {}", error))
+}
+
+// The browser only lets pages at the clipboard from inside input events, so
+// the web build makes do with the console.
+#[cfg(target_arch = "wasm32")]
+pub fn copy_to_clipboard(_text: &str) -> Result<(), String> {
+    Err("The web version cannot copy, the code is in the browser console".to_owned())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn paste_from_clipboard() -> Result<String, String> {
+    Err("The web version cannot paste from the clipboard".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts() -> Vec<Structure> {
+        let block = |kind, facing, position| Block {
+            kind,
+            facing,
+            position,
+        };
+        vec![
+            Structure {
+                blocks: vec![
+                    block(BlockKind::Structure, BlockFacing::Pz, (0, 0, 0)),
+                    block(BlockKind::TractorBeamSource, BlockFacing::Nx, (-3, 12, 1)),
+                ],
+            },
+            Structure {
+                blocks: vec![block(
+                    BlockKind::WelderBeamSource,
+                    BlockFacing::Ny,
+                    (200, -150, 4),
+                )],
+            },
+        ]
+    }
+
+    /// A code around the given bytes, deflated unless told otherwise, with a
+    /// checksum that matches them.
+    fn code_for(bytes: Vec<u8>, deflate: bool) -> String {
+        let bytes = if deflate {
+            compress_to_vec(&bytes, 10)
+        } else {
+            bytes
+        };
+        let mut bytes = [&[VERSION][..], &bytes].concat();
+        bytes.extend(checksum(&bytes).to_le_bytes());
+        format!("{}{}", PREFIX, to_base64(&bytes))
+    }
+
+    #[test]
+    fn codes_round_trip() {
+        let code = encode(7, &parts());
+        assert_eq!(decode(&code), Ok((7, parts())));
+        assert_eq!(decode(&format!("  {}\n", code)), Ok((7, parts())));
+        assert_eq!(decode(&encode(0, &[])), Ok((0, vec![])));
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        for number in [0, 1, -1, 63, -64, 64, 1000, -1000, i32::MAX, i32::MIN] {
+            let mut bytes = Vec::new();
+            push_number(&mut bytes, number);
+            assert_eq!(read_number(&mut bytes.into_iter()), Some(number));
+        }
+    }
+
+    #[test]
+    fn cut_off_codes_are_rejected() {
+        let code = encode(7, &parts());
+        for length in PREFIX.len()..code.len() {
+            assert!(decode(&code[..length]).is_err(), "{}", &code[..length]);
+        }
+    }
+
+    #[test]
+    fn bad_checksums_are_rejected() {
+        let code = encode(7, &parts());
+        let mut bytes = from_base64(&code[PREFIX.len()..]).unwrap();
+        bytes[3] ^= 1;
+        let damaged = format!("{}{}", PREFIX, to_base64(&bytes));
+        assert_eq!(decode(&damaged), Err("the code is damaged"));
+    }
+
+    #[test]
+    fn overflowing_positions_are_rejected() {
+        let mut bytes = vec![0];
+        push_number(&mut bytes, 1);
+        push_number(&mut bytes, 2);
+        for delta in [i32::MAX, i32::MAX] {
+            bytes.push(0);
+            push_number(&mut bytes, delta);
+            push_number(&mut bytes, 0);
+            push_number(&mut bytes, 0);
+        }
+        assert_eq!(decode(&code_for(bytes, true)), Err("the code is damaged"));
+    }
+
+    #[test]
+    fn codes_that_do_not_inflate_are_rejected() {
+        let code = code_for(vec![0xff, 0xff, 0xff], false);
+        assert_eq!(decode(&code), Err("the code is damaged"));
+    }
+
+    #[test]
+    fn rows_of_blocks_make_short_codes() {
+        let row = |y| Structure {
+            blocks: (0..20)
+                .map(|x| Block {
+                    kind: BlockKind::Structure,
+                    facing: BlockFacing::Px,
+                    position: (x, y, 0),
+                })
+                .collect(),
+        };
+        let parts: Vec<_> = (0..10).map(row).collect();
+        let code = encode(3, &parts);
+        assert_eq!(decode(&code), Ok((3, parts)));
+        // Without deflating it would take four bytes a block, 800 in all.
+        assert!(code.len() < 200, "{}", code);
+    }
+}
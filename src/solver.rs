@@ -0,0 +1,528 @@
+//! Looks for a machine that solves a level by trying small ones. The
+//! simulation rules run on plain lists of parts, so nothing has to be drawn,
+//! for `cargo run --release -- --solve assets/levels/1.level.txt`.
+//!
+//! Machines are put together from gadgets, either a single tractor or welder
+//! beam source whose beam crosses an input or the output, or a bridge of
+//! structure blocks under the way from an input to the output. Gadgets only go
+//! in a box around the inputs and the output, pieces that would fall get a path
+//! of structure blocks down to the floor, and machines with fewer gadgets are
+//! tried first.
+
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    block::{Block, BlockFacing, BlockKind},
+    level_format::{parse_level, write_level, Level, Section},
+    reference_scores::Score,
+    simulation::{step, StepChange},
+    structure::Structure,
+    world::{Part, Position},
+};
+
+/// Machines with more gadgets than this are not tried.
+const MAX_GADGETS: usize = 3;
+/// How far past the inputs and the output blocks are placed.
+const MARGIN: i32 = 2;
+/// A machine that has not collected its first output by then is not going to.
+const FIRST_OUTPUT_CYCLES: usize = 100;
+const MAX_CYCLES: usize = 400;
+/// How many machines are simulated before giving up, which takes in the
+/// order of a minute.
+const MAX_MACHINES: usize = 20000;
+
+fn part(structure: Structure, is_hologram: bool) -> Part {
+    Part {
+        structure,
+        // Nothing is spawned, so there is nothing for this to point at.
+        physical_instance: Entity::from_raw(0),
+        is_hologram,
+    }
+}
+
+struct Simulation {
+    parts: Vec<Part>,
+    inputs: Vec<Structure>,
+    outputs: Vec<Structure>,
+    existing_parts: usize,
+    collected_outputs: usize,
+    cycles: usize,
+    /// Anything below this has fallen past the floor.
+    lowest: i32,
+}
+
+impl Simulation {
+    /// The level as `import_level` and `begin_simulation` leave it, with
+    /// `machine` as the user parts. Parts already in the level are left out.
+    fn new(level: &Level, machine: &Structure) -> Self {
+        let mut parts = Vec::new();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (section, structure) in level {
+            match section {
+                Section::Floor => parts.push(part(structure.clone(), false)),
+                Section::Input => {
+                    parts.push(part(structure.clone(), true));
+                    inputs.push(structure.clone());
+                }
+                Section::Output => {
+                    parts.push(part(structure.clone(), true));
+                    outputs.push(structure.clone());
+                }
+                Section::Part => (),
+            }
+        }
+        for piece in machine.loose_pieces() {
+            parts.push(part(piece, false));
+        }
+        let lowest = parts[0]
+            .structure
+            .blocks
+            .iter()
+            .map(|block| block.position.2)
+            .min()
+            .unwrap_or(0);
+        Self {
+            lowest,
+            existing_parts: parts.len(),
+            parts,
+            inputs,
+            outputs,
+            collected_outputs: 0,
+            cycles: 0,
+        }
+    }
+
+    /// One tick of `run_simulation`. `Err` with the cycles once the level is
+    /// complete, otherwise whether anything happened.
+    fn tick(&mut self) -> Result<bool, usize> {
+        self.cycles += 1;
+        let step = step(
+            &mut self.parts,
+            &self.inputs,
+            &self.outputs,
+            self.existing_parts,
+            self.collected_outputs,
+        );
+        if step.complete {
+            return Err(self.cycles - 1);
+        }
+        self.collected_outputs += step
+            .changes
+            .iter()
+            .filter(|change| matches!(change, StepChange::Collected(_)))
+            .count();
+        Ok(!step.changes.is_empty() || !step.plan.moves.is_empty())
+    }
+
+    /// The cycles it takes to collect every output, if it ever does.
+    fn run(&mut self) -> Option<usize> {
+        while self.cycles < MAX_CYCLES {
+            match self.tick() {
+                Err(cycles) => return Some(cycles),
+                // Nothing changed, so nothing ever will.
+                Ok(false) => return None,
+                Ok(true) => (),
+            }
+            let fell = self.parts.iter().any(|part| {
+                !part.is_hologram
+                    && part
+                        .structure
+                        .blocks
+                        .iter()
+                        .any(|block| block.position.2 < self.lowest)
+            });
+            if fell || (self.collected_outputs == 0 && self.cycles > FIRST_OUTPUT_CYCLES) {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+fn add(a: Position, b: Position) -> Position {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn is_beam(block: &Block) -> bool {
+    block.kind == BlockKind::TractorBeamSource || block.kind == BlockKind::WelderBeamSource
+}
+
+/// What the search knows about a level, all cut down to the region it places
+/// blocks in.
+struct Search {
+    /// The level with the floor cut down to the region, which the simulation
+    /// gets a lot faster from.
+    level: Level,
+    min: Position,
+    max: Position,
+    solid: HashSet<Position>,
+    /// Where inputs spawn.
+    inputs: HashSet<Position>,
+    outputs: HashSet<Position>,
+    /// Cells between an input and the output in a straight line, which inputs
+    /// are likely to be pulled through.
+    lanes: HashSet<Position>,
+}
+
+impl Search {
+    fn new(level: &Level) -> Self {
+        let positions = |of: Section| -> HashSet<Position> {
+            level
+                .iter()
+                .filter(|(section, _)| *section == of)
+                .flat_map(|(_, structure)| structure.blocks.iter().map(|block| block.position))
+                .collect()
+        };
+        let inputs = positions(Section::Input);
+        let outputs = positions(Section::Output);
+        let mut min = (i32::MAX, i32::MAX, i32::MAX);
+        let mut max = (i32::MIN, i32::MIN, i32::MIN);
+        for &(x, y, z) in inputs.iter().chain(&outputs) {
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        }
+        // Room to bridge gaps under the inputs.
+        let min = (min.0 - MARGIN, min.1 - MARGIN, min.2 - 1);
+        let max = (max.0 + MARGIN, max.1 + MARGIN, max.2);
+
+        let mut cut_level = Vec::new();
+        let mut solid = HashSet::new();
+        for (section, structure) in level {
+            let mut structure = structure.clone();
+            if *section == Section::Floor {
+                // Whatever is just outside still holds up things at the edge.
+                structure.blocks.retain(|block| {
+                    let (x, y, z) = block.position;
+                    (min.0 - 1..=max.0 + 1).contains(&x)
+                        && (min.1 - 1..=max.1 + 1).contains(&y)
+                        && (min.2 - 1..=max.2 + 1).contains(&z)
+                });
+                solid.extend(structure.blocks.iter().map(|block| block.position));
+            }
+            if *section != Section::Part {
+                cut_level.push((*section, structure));
+            }
+        }
+
+        let mut lanes = HashSet::new();
+        for &a in &inputs {
+            for &b in &outputs {
+                if (a.0, a.2) == (b.0, b.2) {
+                    lanes.extend((a.1.min(b.1)..=a.1.max(b.1)).map(|y| (a.0, y, a.2)));
+                } else if (a.1, a.2) == (b.1, b.2) {
+                    lanes.extend((a.0.min(b.0)..=a.0.max(b.0)).map(|x| (x, a.1, a.2)));
+                }
+            }
+        }
+        Self {
+            level: cut_level,
+            min,
+            max,
+            solid,
+            inputs,
+            outputs,
+            lanes,
+        }
+    }
+
+    fn contains(&self, (x, y, z): Position) -> bool {
+        (self.min.0..=self.max.0).contains(&x)
+            && (self.min.1..=self.max.1).contains(&y)
+            && (self.min.2..=self.max.2).contains(&z)
+    }
+
+    fn free(&self, position: Position) -> bool {
+        !self.solid.contains(&position)
+            && !self.inputs.contains(&position)
+            && !self.outputs.contains(&position)
+    }
+
+    /// The cells a beam goes through before it hits the floor.
+    fn ray(&self, block: &Block) -> impl Iterator<Item = Position> + '_ {
+        let (position, o) = (block.position, block.facing.offset());
+        (1..100)
+            .map(move |distance| add(position, (distance * o.0, distance * o.1, distance * o.2)))
+            .take_while(|position| !self.solid.contains(position))
+    }
+
+    /// Single tractor beam sources whose beam crosses an input or the output,
+    /// welder beam sources whose beam crosses the output, and bridges under
+    /// the lanes.
+    fn gadgets(&self) -> Vec<Structure> {
+        let mut gadgets = Vec::new();
+        // Not in the layer under the inputs, that is only there for bridges.
+        for z in self.min.2 + 1..=self.max.2 {
+            for y in self.min.1..=self.max.1 {
+                for x in self.min.0..=self.max.0 {
+                    let position = (x, y, z);
+                    if !self.free(position) || self.lanes.contains(&position) {
+                        continue;
+                    }
+                    for kind in [BlockKind::TractorBeamSource, BlockKind::WelderBeamSource] {
+                        for facing in BlockFacing::all() {
+                            let block = Block {
+                                kind,
+                                facing,
+                                position,
+                            };
+                            // Welding only helps at the output.
+                            let useful = self.ray(&block).any(|x| {
+                                self.outputs.contains(&x)
+                                    || kind == BlockKind::TractorBeamSource
+                                        && self.inputs.contains(&x)
+                            });
+                            if useful {
+                                gadgets.push(Structure {
+                                    blocks: vec![block],
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let bridge: Vec<Block> = self
+            .lanes
+            .iter()
+            .map(|&position| add(position, (0, 0, -1)))
+            .filter(|&below| {
+                self.contains(below) && self.free(below) && !self.lanes.contains(&below)
+            })
+            .map(|position| Block {
+                kind: BlockKind::Structure,
+                facing: BlockFacing::Pz,
+                position,
+            })
+            .collect();
+        gadgets.extend((Structure { blocks: bridge }).loose_pieces());
+        // The most likely ones first, since there are often too many to try
+        // every combination.
+        gadgets.sort_by_key(|gadget| (self.rank(gadget), gadget.blocks.len()));
+        gadgets
+    }
+
+    /// Lower for gadgets that are more likely to be part of a solution.
+    fn rank(&self, gadget: &Structure) -> u8 {
+        let block = &gadget.blocks[0];
+        match block.kind {
+            // Pulls inputs through the output, so it stops them there.
+            BlockKind::TractorBeamSource => {
+                let mut ray = self.ray(block).skip_while(|x| !self.outputs.contains(x));
+                if ray.any(|x| self.inputs.contains(&x)) {
+                    0
+                } else {
+                    3
+                }
+            }
+            BlockKind::Structure => 1,
+            _ => 2,
+        }
+    }
+
+    /// Adds structure blocks so that every piece of the machine stands on the
+    /// floor, going around the lanes and beams. `None` if a piece cannot.
+    fn supported(&self, machine: &Structure) -> Option<Structure> {
+        let mut machine = machine.clone();
+        let beams: HashSet<Position> = machine
+            .blocks
+            .iter()
+            .filter(|block| is_beam(block))
+            .flat_map(|block| self.ray(block))
+            .collect();
+        let stands = |position: Position, machine: &Structure| {
+            let below = add(position, (0, 0, -1));
+            self.solid.contains(&below) || machine.get_block_at(below).is_some()
+        };
+        for piece in machine.loose_pieces() {
+            if piece
+                .blocks
+                .iter()
+                .any(|block| stands(block.position, &machine))
+            {
+                continue;
+            }
+            // Breadth first, so the path is as short as it can be.
+            let mut came_from = HashMap::new();
+            let mut queue: VecDeque<Position> =
+                piece.blocks.iter().map(|block| block.position).collect();
+            let mut end = None;
+            while let Some(position) = queue.pop_front() {
+                for direction in BlockFacing::all() {
+                    let next = add(position, direction.offset());
+                    let open = self.contains(next)
+                        && self.free(next)
+                        && !self.lanes.contains(&next)
+                        && !beams.contains(&next)
+                        && machine.get_block_at(next).is_none()
+                        && !came_from.contains_key(&next);
+                    if !open {
+                        continue;
+                    }
+                    came_from.insert(next, position);
+                    if stands(next, &machine) {
+                        end = Some(next);
+                        break;
+                    }
+                    queue.push_back(next);
+                }
+                if end.is_some() {
+                    break;
+                }
+            }
+            let mut position = end?;
+            while let Some(&previous) = came_from.get(&position) {
+                machine.blocks.push(Block {
+                    kind: BlockKind::Structure,
+                    facing: BlockFacing::Pz,
+                    position,
+                });
+                position = previous;
+            }
+        }
+        Some(machine)
+    }
+}
+
+pub struct Solution {
+    pub machine: Structure,
+    pub score: Score,
+}
+
+/// Calls `each` with every set of `count` gadgets that do not overlap, until
+/// it returns true.
+fn combinations(
+    gadgets: &[Structure],
+    count: usize,
+    each: &mut impl FnMut(&Structure) -> bool,
+) -> bool {
+    fn go(
+        gadgets: &[Structure],
+        start: usize,
+        count: usize,
+        machine: &mut Structure,
+        each: &mut impl FnMut(&Structure) -> bool,
+    ) -> bool {
+        if count == 0 {
+            return each(machine);
+        }
+        for index in start..gadgets.len() {
+            let gadget = &gadgets[index];
+            let overlaps = gadget
+                .blocks
+                .iter()
+                .any(|block| machine.get_block_at(block.position).is_some());
+            if overlaps {
+                continue;
+            }
+            let before = machine.blocks.len();
+            machine.blocks.extend(gadget.blocks.iter().copied());
+            if go(gadgets, index + 1, count - 1, machine, each) {
+                return true;
+            }
+            machine.blocks.truncate(before);
+        }
+        false
+    }
+    let mut machine = Structure { blocks: Vec::new() };
+    go(gadgets, 0, count, &mut machine, each)
+}
+
+/// The first machine found, from the ones with the fewest gadgets.
+pub fn solve(level: &Level) -> Result<Solution, String> {
+    let search = Search::new(level);
+    let gadgets = search.gadgets();
+    let mut solution = None;
+    let mut tried = 0;
+    for count in 1..=MAX_GADGETS {
+        combinations(&gadgets, count, &mut |machine| {
+            // Nothing moves without a tractor beam on an input.
+            let pulls = machine.blocks.iter().any(|block| {
+                block.kind == BlockKind::TractorBeamSource
+                    && search.ray(block).any(|x| search.inputs.contains(&x))
+            });
+            let machine = match search.supported(machine) {
+                Some(machine) if pulls => machine,
+                _ => return false,
+            };
+            tried += 1;
+            if let Some(cycles) = Simulation::new(&search.level, &machine).run() {
+                let score = (
+                    cycles as u32,
+                    machine.blocks.len() as u32,
+                    machine.loose_pieces().len() as u32,
+                );
+                solution = Some(Solution { machine, score });
+            }
+            solution.is_some() || tried >= MAX_MACHINES
+        });
+        if let Some(solution) = solution {
+            return Ok(solution);
+        }
+        if tried >= MAX_MACHINES {
+            return Err(format!(
+                "gave up after {} machines of up to {} gadgets",
+                tried, count
+            ));
+        }
+    }
+    Err(format!(
+        "none of the {} machines of up to {} gadgets work",
+        tried, MAX_GADGETS
+    ))
+}
+
+/// Prints a solution for each level file, with its parts in the level format
+/// so they can be pasted in after the output.
+pub fn solve_files(paths: &[String]) {
+    for path in paths {
+        let level = match std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| parse_level(&text))
+        {
+            Ok(level) => level,
+            Err(error) => {
+                println!("Could not read {}: {}", path, error);
+                continue;
+            }
+        };
+        match solve(&level) {
+            Ok(Solution { machine, score }) => {
+                let (cycles, blocks, parts) = score;
+                println!(
+                    "{}: {} cycles, {} blocks, {} parts",
+                    path, cycles, blocks, parts
+                );
+                let parts: Level = machine
+                    .loose_pieces()
+                    .into_iter()
+                    .map(|piece| (Section::Part, piece))
+                    .collect();
+                // Without the version line.
+                for line in write_level(&parts).lines().skip(1) {
+                    println!("{}", line);
+                }
+            }
+            Err(error) => println!("{}: {}", path, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solves_the_first_level() {
+        let level = parse_level(include_str!("../assets/levels/0.level.txt")).unwrap();
+        let solution = solve(&level).unwrap();
+        assert_eq!(solution.score, (41, 1, 1));
+        let mut simulation = Simulation::new(&level, &solution.machine);
+        assert_eq!(simulation.run(), Some(41));
+    }
+}
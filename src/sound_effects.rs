@@ -0,0 +1,61 @@
+//! Sounds for things happening in the simulation. Every kind of event has a
+//! cooldown so that running at full speed does not turn into noise.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{audio_settings::AudioSettings, simulation::SimulationEvent, Sfx};
+
+struct EffectSound<'a> {
+    sound: &'a Handle<AudioSource>,
+    volume: f32,
+    speed: f32,
+    /// Seconds before the same kind of event can make a sound again.
+    cooldown: f64,
+}
+
+fn sound_for(event: SimulationEvent, sfx: &Sfx) -> EffectSound<'_> {
+    let (sound, volume, speed, cooldown) = match event {
+        SimulationEvent::InputSpawned => (&sfx.place[0], 0.15, 0.8, 0.3),
+        SimulationEvent::Welded => (&sfx.place[2], 0.35, 1.5, 0.1),
+        SimulationEvent::OutputCollected => (&sfx.ding, 0.5, 1.0, 0.0),
+        SimulationEvent::LevelComplete => (&sfx.level_complete, 0.3, 1.0, 0.0),
+        SimulationEvent::PartStartedMoving => (&sfx.click, 0.1, 0.7, 0.25),
+        SimulationEvent::PartStopped => (&sfx.place[1], 0.15, 0.6, 0.25),
+        SimulationEvent::PushBlocked => (&sfx.click, 0.2, 0.5, 0.5),
+    };
+    EffectSound {
+        sound,
+        volume,
+        speed,
+        cooldown,
+    }
+}
+
+fn simulation_sound_system(
+    mut events: EventReader<SimulationEvent>,
+    time: Res<Time>,
+    sfx: Res<Sfx>,
+    audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
+    mut last_played: Local<HashMap<SimulationEvent, f64>>,
+) {
+    let now = time.seconds_since_startup();
+    for &event in events.iter() {
+        let effect = sound_for(event, &*sfx);
+        if let Some(&last) = last_played.get(&event) {
+            if now - last < effect.cooldown {
+                continue;
+            }
+        }
+        last_played.insert(event, now);
+        audio_settings.play_with_speed(&*audio, effect.sound, effect.volume, effect.speed);
+    }
+}
+
+pub struct SoundEffectsPlugin;
+
+impl Plugin for SoundEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(simulation_sound_system);
+    }
+}
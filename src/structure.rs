@@ -2,12 +2,14 @@ use bevy::{pbr::NotShadowCaster, prelude::*, utils::HashSet};
 use bevy_mod_raycast::RayCastMesh;
 
 use crate::{
-    block::{Block, BlockFacing, BlockKind, BlockRaycastSet},
+    block::{Block, BlockFacing, BlockKind, BlockRaycastSet, BlockVisual},
     hologramify::PleaseHologramifyThis,
-    world::Position, setup::LevelEntity,
+    setup::LevelEntity,
+    world::Position,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Structure {
     pub blocks: Vec<Block>,
 }
@@ -32,6 +34,92 @@ impl Structure {
         }
     }
 
+    /// Moves the structure so that its lowest block sits at the origin.
+    pub fn normalize(&mut self) {
+        let anchor = self
+            .blocks
+            .iter()
+            .map(|block| block.position)
+            .min_by_key(|&(x, y, z)| (z, x, y));
+        if let Some(anchor) = anchor {
+            self.translate((-anchor.0, -anchor.1, -anchor.2));
+        }
+    }
+
+    /// Returns a copy of this structure rotated around the origin so that
+    /// whatever was facing +X is now facing `facing`.
+    pub fn rotated(&self, facing: BlockFacing) -> Structure {
+        let rotation = facing.rotation();
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let p = block.position;
+                let rotated = rotation * Vec3::new(p.0 as f32, p.1 as f32, p.2 as f32);
+                Block {
+                    kind: block.kind,
+                    facing: block.facing.rotated_by(rotation),
+                    position: (
+                        rotated.x.round() as i32,
+                        rotated.y.round() as i32,
+                        rotated.z.round() as i32,
+                    ),
+                }
+            })
+            .collect();
+        let mut rotated = Structure { blocks };
+        rotated.normalize();
+        rotated
+    }
+
+    fn neighbors(of: Position, into: &mut HashSet<Position>, list: &mut Vec<Position>) {
+        for pos in [
+            (of.0 + 1, of.1, of.2),
+            (of.0 - 1, of.1, of.2),
+            (of.0, of.1 + 1, of.2),
+            (of.0, of.1 - 1, of.2),
+            (of.0, of.1, of.2 + 1),
+            (of.0, of.1, of.2 - 1),
+        ]
+        .into_iter()
+        {
+            if into.insert(pos) {
+                list.push(pos);
+            }
+        }
+    }
+
+    /// Returns the pieces this structure falls apart into when the simulation
+    /// starts, i.e. its groups of face-connected blocks.
+    pub fn loose_pieces(&self) -> Vec<Structure> {
+        let mut pieces = Vec::new();
+        let mut indices = Vec::new();
+        let mut positions = HashSet::new();
+        let mut positions_list = Vec::new();
+        let mut blocks = self.blocks.clone();
+        while blocks.len() > 0 {
+            Self::neighbors(blocks[0].position, &mut positions, &mut positions_list);
+            positions.insert(blocks[0].position);
+            indices.push(0);
+            while let Some(position) = positions_list.pop() {
+                if let Some(index) = blocks.iter().position(|x| x.position == position) {
+                    Self::neighbors(position, &mut positions, &mut positions_list);
+                    indices.push(index);
+                }
+            }
+            indices.sort();
+            let mut extracted_blocks = Vec::new();
+            for index in indices.into_iter().rev() {
+                extracted_blocks.push(blocks.swap_remove(index));
+            }
+            pieces.push(Structure {
+                blocks: extracted_blocks,
+            });
+            indices = Vec::new();
+        }
+        pieces
+    }
+
     pub fn get_block_at(&self, position: Position) -> Option<&Block> {
         self.blocks.iter().find(|x| x.position == position)
     }
@@ -81,6 +169,21 @@ fn spawn_block(
     is_hologram: bool,
 ) -> Entity {
     let bbox = assets.load::<Mesh, _>("blocks/bounding_box.obj");
+    let entity = spawn_block_visuals(commands, block, assets, is_hologram);
+    commands
+        .entity(entity)
+        // This will not be rendered since there is no material attached.
+        .insert(bbox)
+        .insert(RayCastMesh::<BlockRaycastSet>::default());
+    entity
+}
+
+fn spawn_block_visuals(
+    commands: &mut Commands,
+    block: &Block,
+    assets: &AssetServer,
+    is_hologram: bool,
+) -> Entity {
     let scene = assets.load(block.kind.asset_name());
     let mut commands = commands.spawn();
     commands
@@ -94,10 +197,8 @@ fn spawn_block(
             .with_rotation(block.facing.rotation()),
             ..Default::default()
         })
-        // This will not be rendered since there is no material attached.
-        .insert(bbox)
-        .insert(RayCastMesh::<BlockRaycastSet>::default())
         .insert(NotShadowCaster)
+        .insert(BlockVisual(block.kind))
         .insert(LevelEntity);
     if is_hologram {
         commands.insert(PleaseHologramifyThis::default());
@@ -105,6 +206,18 @@ fn spawn_block(
     commands.id()
 }
 
+/// What a beam did on the last tick.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BeamState {
+    /// Hitting nothing, or something it has no effect on.
+    Idle,
+    Pulling,
+    /// Welded parts together on the last tick.
+    Welding,
+    /// Hitting the part it is attached to, or pulling on a part that is stuck.
+    Blocked,
+}
+
 #[derive(Component)]
 pub struct Beam {
     pub for_block: Block,
@@ -152,3 +265,102 @@ pub fn spawn_structure(
 
     root
 }
+
+/// Spawns a hologram of the structure which cannot be hit by the block raycast,
+/// for previewing things that have not been placed yet.
+pub fn spawn_ghost_structure(
+    structure: &Structure,
+    commands: &mut Commands,
+    assets: &AssetServer,
+) -> Entity {
+    let root = commands
+        .spawn()
+        .insert_bundle(SpatialBundle::default())
+        .insert(LevelEntity)
+        .id();
+
+    for block in &structure.blocks {
+        let block = spawn_block_visuals(commands, block, assets, true);
+        commands.entity(root).add_child(block);
+    }
+
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structure(blocks: &[(Position, BlockFacing)]) -> Structure {
+        Structure {
+            blocks: blocks
+                .iter()
+                .map(|&(position, facing)| Block {
+                    kind: BlockKind::TractorBeamSource,
+                    facing,
+                    position,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn normalize_moves_the_lowest_block_to_the_origin() {
+        let mut moved = structure(&[
+            ((5, 3, 2), BlockFacing::Px),
+            ((4, 9, 1), BlockFacing::Py),
+            ((7, 2, 1), BlockFacing::Nz),
+        ]);
+        moved.normalize();
+        assert_eq!(
+            moved,
+            structure(&[
+                ((1, -6, 1), BlockFacing::Px),
+                ((0, 0, 0), BlockFacing::Py),
+                ((3, -7, 0), BlockFacing::Nz),
+            ])
+        );
+        let again = moved.clone();
+        moved.normalize();
+        assert_eq!(moved, again);
+    }
+
+    #[test]
+    fn quarter_turns() {
+        let original = structure(&[
+            ((0, 0, 0), BlockFacing::Px),
+            ((1, 0, 0), BlockFacing::Py),
+            ((1, 0, 1), BlockFacing::Pz),
+        ]);
+        assert_eq!(original.rotated(BlockFacing::Px), original);
+        assert_eq!(
+            original.rotated(BlockFacing::Py),
+            structure(&[
+                ((0, 0, 0), BlockFacing::Py),
+                ((0, 1, 0), BlockFacing::Nx),
+                ((0, 1, 1), BlockFacing::Pz),
+            ])
+        );
+        assert_eq!(
+            original.rotated(BlockFacing::Nx),
+            structure(&[
+                ((1, 0, 0), BlockFacing::Nx),
+                ((0, 0, 0), BlockFacing::Ny),
+                ((0, 0, 1), BlockFacing::Pz),
+            ])
+        );
+        assert_eq!(
+            original.rotated(BlockFacing::Ny),
+            structure(&[
+                ((0, 1, 0), BlockFacing::Ny),
+                ((0, 0, 0), BlockFacing::Px),
+                ((0, 0, 1), BlockFacing::Pz),
+            ])
+        );
+        let mut turned = original.clone();
+        for _ in 0..4 {
+            turned = turned.rotated(BlockFacing::Py);
+        }
+        assert_eq!(turned, original);
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::{asset::HandleId, prelude::*, utils::HashMap};
+
+/// Multiplies the colour of every material underneath the entity it is put on
+/// and adds `emissive` to its glow. Entities further down the hierarchy with
+/// their own `Tint` are left alone.
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct Tint {
+    pub color: Color,
+    pub emissive: Color,
+    /// Replaces the hue of strongly coloured materials, keeping grey ones and
+    /// the brightness as they are. Applied before `color`.
+    pub recolor: Option<Color>,
+}
+
+impl Tint {
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            emissive: Color::BLACK,
+            recolor: None,
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        self.color == Color::WHITE && self.emissive == Color::BLACK && self.recolor.is_none()
+    }
+
+    fn key(&self) -> [u8; 12] {
+        let mut key = [0; 12];
+        let channels = self.color.as_rgba_f32().into_iter();
+        let channels = channels.chain(self.emissive.as_rgba_f32());
+        let channels = channels.chain(self.recolor.map_or([0.0; 4], |x| x.as_rgba_f32()));
+        for (byte, channel) in key.iter_mut().zip(channels) {
+            *byte = (channel.clamp(0.0, 1.0) * 255.0) as u8;
+        }
+        key
+    }
+}
+
+#[derive(Component)]
+struct OriginalMaterial(Handle<StandardMaterial>);
+
+#[derive(Default)]
+struct TintCache(HashMap<(HandleId, [u8; 12]), Handle<StandardMaterial>>);
+
+/// Colours whose channels are further apart than this count as strongly
+/// coloured for `Tint::recolor`.
+const SATURATED: f32 = 0.25;
+
+fn recolor(original: Color, to: Color) -> Color {
+    let [r, g, b, a] = original.as_rgba_f32();
+    let max = r.max(g).max(b);
+    if max - r.min(g).min(b) < SATURATED {
+        return original;
+    }
+    let [tr, tg, tb, _] = to.as_rgba_f32();
+    let scale = max / tr.max(tg).max(tb).max(0.001);
+    Color::rgba(tr * scale, tg * scale, tb * scale, a)
+}
+
+fn tint_material(original: &StandardMaterial, tint: Tint) -> StandardMaterial {
+    let (base, emissive) = match tint.recolor {
+        Some(to) => (
+            recolor(original.base_color, to),
+            recolor(original.emissive, to),
+        ),
+        None => (original.base_color, original.emissive),
+    };
+    let color = tint.color;
+    StandardMaterial {
+        base_color: Color::rgba(
+            base.r() * color.r(),
+            base.g() * color.g(),
+            base.b() * color.b(),
+            base.a() * color.a(),
+        ),
+        emissive: emissive + tint.emissive,
+        ..original.clone()
+    }
+}
+
+fn collect_meshes(
+    entity: Entity,
+    children_query: &Query<&Children>,
+    nested_tints: &Query<(), With<Tint>>,
+    into: &mut Vec<Entity>,
+) {
+    into.push(entity);
+    if let Ok(children) = children_query.get(entity) {
+        for &child in children.iter() {
+            if nested_tints.get(child).is_err() {
+                collect_meshes(child, children_query, nested_tints, into);
+            }
+        }
+    }
+}
+
+fn tint_system(
+    mut commands: Commands,
+    tints: Query<(Entity, &Tint)>,
+    nested_tints: Query<(), With<Tint>>,
+    children_query: Query<&Children>,
+    mut meshes: Query<(&mut Handle<StandardMaterial>, Option<&OriginalMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut cache: Local<TintCache>,
+) {
+    let mut entities = Vec::new();
+    for (root, &tint) in tints.iter() {
+        entities.clear();
+        collect_meshes(root, &children_query, &nested_tints, &mut entities);
+        for &entity in &entities {
+            let (mut handle, original) = match meshes.get_mut(entity) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let original = match original {
+                Some(original) => original.0.clone(),
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(OriginalMaterial(handle.clone()));
+                    handle.clone()
+                }
+            };
+            let target = if tint.is_identity() {
+                original
+            } else if let Some(target) = cache.0.get(&(original.id, tint.key())) {
+                target.clone()
+            } else if let Some(material) = materials.get(&original) {
+                let tinted = tint_material(material, tint);
+                let target = materials.add(tinted);
+                cache.0.insert((original.id, tint.key()), target.clone());
+                target
+            } else {
+                continue;
+            };
+            if *handle != target {
+                *handle = target;
+            }
+        }
+    }
+}
+
+pub struct TintPlugin;
+
+impl Plugin for TintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, tint_system);
+    }
+}
@@ -1,5 +1,7 @@
 mod base;
+mod description;
 mod parts;
 
 pub mod setup;
 pub use base::{Part, Position, World, WorldSnapshot};
+pub use description::WorldDescription;
@@ -0,0 +1,128 @@
+//! A copy of a `World` without any entities in it, for tools outside the game.
+//! With the `serde` feature it can be written out as JSON or RON.
+
+use super::World;
+use crate::{
+    level_format::{Level, Section},
+    structure::Structure,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartDescription {
+    pub role: Section,
+    pub structure: Structure,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldDescription {
+    pub parts: Vec<PartDescription>,
+}
+
+impl WorldDescription {
+    pub fn from_world(world: &World, first_user_part: usize) -> Self {
+        let parts = world
+            .parts()
+            .iter()
+            .enumerate()
+            .map(|(index, part)| PartDescription {
+                role: if index == 0 {
+                    Section::Floor
+                } else if index == first_user_part - 1 {
+                    Section::Output
+                } else if index < first_user_part {
+                    Section::Input
+                } else {
+                    Section::Part
+                },
+                structure: part.structure.clone(),
+            })
+            .collect();
+        Self { parts }
+    }
+
+    pub fn to_level(&self) -> Level {
+        self.parts
+            .iter()
+            .map(|part| (part.role, part.structure.clone()))
+            .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl WorldDescription {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+    }
+
+    // Only the tests read descriptions back so far.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|error| error.to_string())
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+    }
+
+    // Only the tests read descriptions back so far.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|error| error.to_string())
+    }
+
+    /// Writes `<name>.json` and `<name>.ron` into the working directory.
+    pub fn save(&self, name: &str) {
+        for (extension, text) in [("json", self.to_json()), ("ron", self.to_ron())] {
+            let file_name = format!("{}.{}", name, extension);
+            let result = text.and_then(|text| {
+                std::fs::write(&file_name, text).map_err(|error| error.to_string())
+            });
+            match result {
+                Ok(()) => println!("Saved {}", file_name),
+                Err(error) => println!("Could not save {}: {}", file_name, error),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::block::{Block, BlockFacing, BlockKind};
+
+    fn description() -> WorldDescription {
+        let part = |role, kind, position| PartDescription {
+            role,
+            structure: Structure {
+                blocks: vec![Block {
+                    kind,
+                    facing: BlockFacing::Ny,
+                    position,
+                }],
+            },
+        };
+        WorldDescription {
+            parts: vec![
+                part(Section::Floor, BlockKind::DecoStructure, (0, 0, -1)),
+                part(Section::Input, BlockKind::DecoStructure2, (0, 0, 0)),
+                part(Section::Output, BlockKind::DecoStructure2, (3, -2, 0)),
+                part(Section::Part, BlockKind::TractorBeamSource, (1, 0, 0)),
+            ],
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = description().to_json().unwrap();
+        assert_eq!(WorldDescription::from_json(&json), Ok(description()));
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let ron = description().to_ron().unwrap();
+        assert_eq!(WorldDescription::from_ron(&ron), Ok(description()));
+    }
+}
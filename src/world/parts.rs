@@ -1,18 +1,28 @@
-use bevy::{prelude::*, utils::HashSet};
+use bevy::prelude::*;
 
-use super::{Part, Position, World};
+use super::{Part, World};
 use crate::{
-    animations::Animation,
+    animations::{Animation, PendingStructure},
     setup::LevelEntity,
     structure::{spawn_structure, Structure},
 };
 
 impl World {
+    /// Spawns the part's structure under its physical instance. Whatever was
+    /// there before is only removed once the new scenes have spawned, see
+    /// `PendingStructure`.
     fn update_part(part: &Part, commands: &mut Commands, assets: &AssetServer) {
         let structure = spawn_structure(&part.structure, commands, assets, part.is_hologram);
-        let mut commands = commands.entity(part.physical_instance);
-        commands.despawn_descendants();
-        commands.add_child(structure);
+        commands
+            .entity(structure)
+            .insert(PendingStructure::default());
+        commands.entity(part.physical_instance).add_child(structure);
+    }
+
+    fn push_part(&mut self, part: Part, commands: &mut Commands, assets: &AssetServer) {
+        Self::update_part(&part, commands, assets);
+        self.parts.push(part);
+        self.debug_assert_invariants();
     }
 
     fn add_part_impl(
@@ -27,14 +37,12 @@ impl World {
             .insert_bundle(SpatialBundle::default())
             .insert(LevelEntity)
             .id();
-        let index = self.parts.len();
-        self.parts.push(Part {
+        let part = Part {
             structure: part,
             physical_instance: ent,
             is_hologram,
-        });
-        Self::update_part(&self.parts[index], commands, assets);
-        self.debug_assert_invariants();
+        };
+        self.push_part(part, commands, assets);
     }
 
     pub fn add_part(&mut self, part: Structure, commands: &mut Commands, assets: &AssetServer) {
@@ -66,66 +74,73 @@ impl World {
     ) {
         let mut parts: Vec<_> = parts.into_iter().collect();
         parts.sort();
+        // The merged part takes over the physical instance of the first one,
+        // so any animation on it carries on.
+        let kept = parts.remove(0);
         parts.reverse();
         let parts: Vec<_> = parts
             .into_iter()
             .map(|index| self.remove_part(index, commands))
             .collect();
-        let mut new_structure = Structure {
-            blocks: parts
+        let mut kept = self.parts.remove(kept);
+        kept.structure.blocks.extend(
+            parts
                 .into_iter()
-                .flat_map(|part| part.structure.blocks.into_iter())
-                .collect(),
-        };
-        self.add_part(new_structure, commands, assets);
+                .flat_map(|part| part.structure.blocks.into_iter()),
+        );
+        self.push_part(kept, commands, assets);
     }
 
-    fn neighbors(of: Position, into: &mut HashSet<Position>, list: &mut Vec<Position>) {
-        for pos in [
-            (of.0 + 1, of.1, of.2),
-            (of.0 - 1, of.1, of.2),
-            (of.0, of.1 + 1, of.2),
-            (of.0, of.1 - 1, of.2),
-            (of.0, of.1, of.2 + 1),
-            (of.0, of.1, of.2 - 1),
-        ]
-        .into_iter()
-        {
-            if into.insert(pos) {
-                list.push(pos);
-            }
+    /// The first piece keeps the physical instance of the part it came from.
+    fn split_part(&mut self, part: Part, commands: &mut Commands, assets: &AssetServer) {
+        let mut pieces = part.structure.loose_pieces().into_iter();
+        if let Some(first) = pieces.next() {
+            let first = Part {
+                structure: first,
+                ..part
+            };
+            self.push_part(first, commands, assets);
+        } else {
+            commands.entity(part.physical_instance).despawn_recursive();
+        }
+        for piece in pieces {
+            self.add_part(piece, commands, assets);
         }
     }
 
-    fn split_part(&mut self, mut part: Part, commands: &mut Commands, assets: &AssetServer) {
-        let mut indices = Vec::new();
-        let mut positions = HashSet::new();
-        let mut positions_list = Vec::new();
-        let blocks = &mut part.structure.blocks;
-        while blocks.len() > 0 {
-            Self::neighbors(blocks[0].position, &mut positions, &mut positions_list);
-            positions.insert(blocks[0].position);
-            indices.push(0);
-            while let Some(position) = positions_list.pop() {
-                if let Some(index) = blocks.iter().position(|x| x.position == position) {
-                    Self::neighbors(position, &mut positions, &mut positions_list);
-                    indices.push(index);
-                }
-            }
-            indices.sort();
-            let mut extracted_blocks = Vec::new();
-            for index in indices.into_iter().rev() {
-                extracted_blocks.push(blocks.swap_remove(index));
+    /// Lists the parts that `split_loose_parts` would break up, along with how
+    /// many pieces each one would become.
+    pub fn loose_part_report(&self) -> Vec<(usize, usize)> {
+        self.parts
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, part)| !part.is_hologram)
+            .map(|(index, part)| (index, part.structure.loose_pieces().len()))
+            .filter(|&(_, pieces)| pieces > 1)
+            .collect()
+    }
+
+    /// The parts as `split_loose_parts` would leave them, without spawning
+    /// anything. Pieces share the physical instance of the part they came from.
+    pub fn loose_parts(&self) -> Vec<Part> {
+        let mut parts = vec![self.parts[0].clone()];
+        for part in &self.parts[1..] {
+            if part.is_hologram {
+                parts.push(part.clone());
+            } else {
+                parts.extend(
+                    part.structure
+                        .loose_pieces()
+                        .into_iter()
+                        .map(|structure| Part {
+                            structure,
+                            ..part.clone()
+                        }),
+                );
             }
-            self.add_part(
-                Structure {
-                    blocks: extracted_blocks,
-                },
-                commands,
-                assets,
-            );
-            indices = Vec::new();
         }
+        parts
     }
 
     pub fn split_loose_parts(&mut self, commands: &mut Commands, assets: &AssetServer) {
@@ -133,9 +148,8 @@ impl World {
         // Retain the floor as the first part.
         self.parts.push(parts.remove(0));
         for part in parts {
-            commands.entity(part.physical_instance).despawn_recursive();
             if part.is_hologram {
-                self.add_hologram(part.structure, commands, assets);
+                self.parts.push(part);
             } else {
                 self.split_part(part, commands, assets);
             }
@@ -13,12 +13,7 @@ pub fn setup_world(
     global_state: &GlobalState,
 ) -> usize {
     let mut world = World::new();
-    let first_user_part = import_level(
-        &global_state.levels[global_state.current_level],
-        &mut world,
-        commands,
-        assets,
-    );
+    let first_user_part = import_level(&global_state.slot().level, &mut world, commands, assets);
 
     let blank_structure = Structure { blocks: Vec::new() };
     if !EDITING {